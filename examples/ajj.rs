@@ -45,7 +45,7 @@ impl Init4Config for Config {
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let _config_and_guard = init4_bin_base::init::<Config>()?;
+    let _config_and_guard = init4_bin_base::init::<Config>();
 
     let router = Router::<()>::new()
         .route("helloWorld", || async {
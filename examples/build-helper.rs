@@ -24,6 +24,6 @@ fn main() -> eyre::Result<()> {
 
     let _ = signal_hook::flag::register(signal_hook::consts::SIGINT, Arc::clone(&term));
 
-    let _config_and_guard = init4_bin_base::init::<Config>()?;
+    let _config_and_guard = init4_bin_base::init::<Config>();
     Ok(())
 }
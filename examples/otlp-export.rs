@@ -41,7 +41,7 @@ async fn main() -> eyre::Result<()> {
     signal_hook::flag::register(signal_hook::consts::SIGINT, Arc::clone(&term))
         .wrap_err("failed to register SIGINT hook")?;
 
-    let _config_and_guard = init4_bin_base::init::<Config>()?;
+    let _config_and_guard = init4_bin_base::init::<Config>();
     let mut counter = 0;
     let _outer = info_span!("outer span").entered();
 
@@ -1,16 +1,24 @@
-use proc_macro2::TokenStream;
-use quote::quote;
-use syn::{spanned::Spanned, Ident, LitStr};
+use proc_macro2::{TokenStream, TokenTree};
+use quote::{quote, ToTokens};
+use syn::{ext::IdentExt, spanned::Spanned, Expr, ExprLit, Ident, Lit, LitStr};
 
 /// A parsed Field of a struct
 pub(crate) struct Field {
     env_var: Option<LitStr>,
+    aliases: Vec<LitStr>,
+    separator: Option<LitStr>,
+    prefix: Option<LitStr>,
+    default: Option<Expr>,
+    validate: Option<syn::Path>,
     field_name: Option<Ident>,
     field_type: syn::Type,
 
     optional: bool,
     infallible: bool,
+    file: bool,
+    secret: bool,
     skip: bool,
+    generic: bool,
     desc: Option<String>,
 
     _attrs: Vec<syn::Attribute>,
@@ -24,38 +32,53 @@ impl TryFrom<&syn::Field> for Field {
     fn try_from(field: &syn::Field) -> Result<Self, syn::Error> {
         let mut optional = false;
         let mut env_var = None;
+        let mut aliases = Vec::new();
+        let mut separator = None;
+        let mut prefix = None;
+        let mut default = None;
+        let mut validate = None;
         let mut infallible = false;
+        let mut file = false;
+        let mut secret = false;
         let mut desc = None;
         let mut skip = false;
 
-        field
+        for attr in field
             .attrs
             .iter()
             .filter(|attr| attr.path().is_ident("from_env"))
-            .for_each(|attr| {
-                let _ = attr.parse_nested_meta(|meta| {
-                    if meta.path.is_ident("skip") {
-                        skip = true;
-                        return Ok(());
-                    }
-                    if meta.path.is_ident("optional") {
-                        optional = true;
-                        return Ok(());
-                    }
-                    if meta.path.is_ident("var") {
-                        env_var = Some(meta.value()?.parse::<LitStr>()?);
-                        return Ok(());
-                    }
-                    if meta.path.is_ident("desc") {
-                        desc = Some(meta.value()?.parse::<LitStr>()?.value());
-                        return Ok(());
-                    }
-                    if meta.path.is_ident("infallible") {
-                        infallible = true;
-                    }
-                    Ok(())
-                });
-            });
+        {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("skip") {
+                    skip = true;
+                } else if meta.path.is_ident("optional") {
+                    optional = true;
+                } else if meta.path.is_ident("var") {
+                    env_var = Some(meta.value()?.parse::<LitStr>()?);
+                } else if meta.path.is_ident("alias") {
+                    aliases.push(meta.value()?.parse::<LitStr>()?);
+                } else if meta.path.is_ident("separator") {
+                    separator = Some(meta.value()?.parse::<LitStr>()?);
+                } else if meta.path.is_ident("prefix") {
+                    prefix = Some(meta.value()?.parse::<LitStr>()?);
+                } else if meta.path.is_ident("default") {
+                    default = Some(meta.value()?.parse::<Expr>()?);
+                } else if meta.path.is_ident("validate") {
+                    validate = Some(meta.value()?.parse::<syn::Path>()?);
+                } else if meta.path.is_ident("desc") {
+                    desc = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("infallible") {
+                    infallible = true;
+                } else if meta.path.is_ident("file") {
+                    file = true;
+                } else if meta.path.is_ident("secret") {
+                    secret = true;
+                } else {
+                    return Err(meta.error("unknown from_env attribute"));
+                }
+                Ok(())
+            })?;
+        }
 
         if desc.is_none() && env_var.is_some() {
             return Err(syn::Error::new(
@@ -64,17 +87,75 @@ impl TryFrom<&syn::Field> for Field {
             ));
         }
 
+        if prefix.is_some() && env_var.is_some() {
+            return Err(syn::Error::new(
+                field.span(),
+                "`prefix` applies to nested `FromEnv` fields and cannot be combined with `var`",
+            ));
+        }
+
+        if default.is_some() && env_var.is_none() {
+            return Err(syn::Error::new(
+                field.span(),
+                "`default` applies to `FromEnvVar` fields and requires `var`",
+            ));
+        }
+
+        if validate.is_some() && env_var.is_none() {
+            return Err(syn::Error::new(
+                field.span(),
+                "`validate` applies to `FromEnvVar` fields and requires `var`. Use \
+                 `validate_with` on the nested struct instead",
+            ));
+        }
+
+        if !aliases.is_empty() && env_var.is_none() {
+            return Err(syn::Error::new(
+                field.span(),
+                "`alias` applies to `FromEnvVar` fields and requires `var`",
+            ));
+        }
+
+        if separator.is_some() && env_var.is_none() {
+            return Err(syn::Error::new(
+                field.span(),
+                "`separator` applies to `FromEnvVar` fields and requires `var`",
+            ));
+        }
+
+        if file && env_var.is_none() {
+            return Err(syn::Error::new(
+                field.span(),
+                "`file` applies to `FromEnvVar` fields and requires `var`",
+            ));
+        }
+
+        if secret && env_var.is_none() {
+            return Err(syn::Error::new(
+                field.span(),
+                "`secret` applies to `FromEnvVar` fields and requires `var`",
+            ));
+        }
+
         let field_type = field.ty.clone();
         let field_name = field.ident.clone();
         let span = field.span();
 
         Ok(Field {
             env_var,
+            aliases,
+            separator,
+            prefix,
+            default,
+            validate,
             field_name,
             field_type,
             optional,
             skip,
+            generic: false,
             infallible,
+            file,
+            secret,
             desc,
             _attrs: field
                 .attrs
@@ -99,59 +180,367 @@ impl Field {
             .unwrap()
     }
 
-    /// Produces a line for the `inventory` function
-    pub(crate) fn expand_env_item_info(&self) -> TokenStream {
+    /// Record whether the field type mentions any of the generic `params` of
+    /// the struct.
+    pub(crate) fn mark_generic(&mut self, params: &[Ident]) {
+        self.generic = mentions(self.field_type.to_token_stream(), params);
+    }
+
+    /// The bound required for the field to be loaded, if its type is
+    /// generic.
+    pub(crate) fn bound(&self, from_env: &TokenStream) -> Option<TokenStream> {
+        if self.skip || !self.generic {
+            return None;
+        }
+        let field_ty = &self.field_type;
+        if self.env_var.is_some() {
+            Some(quote! { #field_ty: #from_env::FromEnvVar })
+        } else {
+            Some(quote! { #field_ty: #from_env::FromEnv })
+        }
+    }
+
+    /// The key of the field in a config file. This is the field name, or the
+    /// index for tuple structs.
+    pub(crate) fn config_key(&self, idx: usize) -> String {
+        self.field_name
+            .as_ref()
+            .map(|name| name.unraw().to_string())
+            .unwrap_or_else(|| idx.to_string())
+    }
+
+    /// The key of the field in a config file, for fields of enum variants.
+    /// The fields of every variant share a single table, so fields of tuple
+    /// variants are keyed by their lowercased var name, and nested `FromEnv`
+    /// fields of tuple variants are flattened into the table.
+    pub(crate) fn variant_key(&self, idx: usize) -> String {
+        match (&self.field_name, &self.env_var) {
+            (Some(_), _) => self.config_key(idx),
+            (None, Some(env_var)) => env_var.value().to_lowercase(),
+            (None, None) => String::new(),
+        }
+    }
+
+    /// Produces a line for the `inventory` function. The `group` is the name
+    /// of the type the field belongs to, and the `key` its config key.
+    pub(crate) fn expand_env_item_info(
+        &self,
+        from_env: &TokenStream,
+        key: &str,
+        group: &str,
+    ) -> TokenStream {
         if self.skip {
             return quote! {};
         }
 
         let description = self.desc.clone().unwrap_or_default();
-        let optional = self.optional;
+        let optional = self.optional || self.default.is_some();
 
         if let Some(env_var) = &self.env_var {
             let var_name = env_var.value();
+            let field_ty = &self.field_type;
+            let type_name = display_tokens(field_ty);
+            let secret = self.secret;
 
-            return quote! {
-                items.push(&EnvItemInfo {
-                    var: #var_name,
-                    description: #description,
-                    optional: #optional,
-                });
+            let mut item = quote! {
+                #from_env::EnvItemInfo::new(#var_name, #description)
+                    .type_name(#type_name)
+                    .group(#group)
+                    .key(#key)
+                    .values(<#field_ty as #from_env::FromEnvVar>::VALUES)
+                    .secret(#secret || <#field_ty as #from_env::FromEnvVar>::SECRET)
+            };
+            if optional {
+                item.extend(quote! { .optional() });
+            }
+            if let Some(default) = self.default.as_ref().map(display_default) {
+                item.extend(quote! { .default(#default) });
+            }
+            if self.file {
+                item.extend(quote! { .file() });
+            }
+            if !self.aliases.is_empty() {
+                let aliases = &self.aliases;
+                item.extend(quote! { .aliases(&[#(#aliases),*]) });
+            }
+            if let Some(separator) = &self.separator {
+                item.extend(quote! { .separator(#separator) });
+            }
+
+            // Items of generic types depend on the type parameters, so cannot
+            // be promoted to statics, and are interned at runtime instead.
+            return if self.generic {
+                quote! { items.push(#item.intern()); }
+            } else {
+                quote! { items.push(&const { #item }); }
             };
         }
 
         let field_ty = &self.field_type;
+        let prefix = self.prefix.as_ref().map(LitStr::value).unwrap_or_default();
         quote! {
             items.extend(
-                <#field_ty as FromEnv>::inventory()
+                <#field_ty as #from_env::FromEnv>::inventory_with_prefix(#prefix)
+                    .into_iter()
+                    .map(|item| item.with_key_prefix(#key))
             );
         }
     }
 
-    pub(crate) fn expand_item_from_env(&self, idx: usize) -> TokenStream {
-        let field_name = self.field_name(idx);
-
+    /// Produces the lines for the `effective_config_with_prefix` function,
+    /// adding the field, bound to its field name, to `__from_env_config`.
+    pub(crate) fn expand_effective(
+        &self,
+        from_env: &TokenStream,
+        idx: usize,
+        key: &str,
+    ) -> TokenStream {
         if self.skip {
+            return quote! {};
+        }
+
+        let field_name = self.field_name(idx);
+        if let Some(env_var) = &self.env_var {
             return quote! {
-                let #field_name = Default::default();
+                if let ::std::option::Option::Some(__from_env_effective) = #from_env::EffectiveValue::find(
+                    &__from_env_items,
+                    &#from_env::prefixed_var(__from_env_prefix, #env_var),
+                    __from_env_source,
+                ) {
+                    __from_env_config.push_value(__from_env_effective.with_parsed(#field_name));
+                }
             };
         }
 
+        let prefix = self.prefix.as_ref().map(LitStr::value).unwrap_or_default();
+        quote! {
+            __from_env_config.push_child(
+                #key,
+                #from_env::FromEnv::effective_config_with_prefix(
+                    #field_name,
+                    __from_env_source,
+                    &#from_env::prefixed_var(__from_env_prefix, #prefix),
+                ),
+            );
+        }
+    }
+
+    /// Produces the expression that loads the field, without the trailing `?`.
+    fn expand_load(&self, from_env: &TokenStream) -> TokenStream {
         let fn_invoc = if let Some(ref env_var) = self.env_var {
-            quote! { FromEnvVar::from_env_var(#env_var) }
+            let source = if self.file {
+                quote! { &__from_env_file }
+            } else {
+                quote! { __from_env_source }
+            };
+            let load = match (&self.default, &self.separator) {
+                (
+                    Some(Expr::Lit(ExprLit {
+                        lit: Lit::Str(default),
+                        ..
+                    })),
+                    None,
+                ) => quote! {
+                    #from_env::FromEnvVar::from_env_var_in_or_parse(#source, &__from_env_var, #default)
+                },
+                (Some(default), None) => quote! {
+                    #from_env::FromEnvVar::from_env_var_in_or_else(#source, &__from_env_var, || #default)
+                },
+                (None, None) => quote! {
+                    #from_env::FromEnvVar::from_env_var_in(#source, &__from_env_var)
+                },
+                (default, Some(separator)) => {
+                    let load = quote! {
+                        #from_env::FromEnvVar::from_env_var_in_with_separator(
+                            #source,
+                            &__from_env_var,
+                            #separator,
+                        )
+                    };
+                    match default {
+                        Some(Expr::Lit(ExprLit {
+                            lit: Lit::Str(default),
+                            ..
+                        })) => quote! {
+                            match #load {
+                                ::std::result::Result::Err(err) if err.is_missing() => {
+                                    #from_env::FromEnvVar::from_env_var_in_with_separator(
+                                        &#from_env::SingleValue(#default),
+                                        &__from_env_var,
+                                        #separator,
+                                    )
+                                }
+                                res => res,
+                            }
+                        },
+                        Some(default) => quote! {
+                            match #load {
+                                ::std::result::Result::Err(err) if err.is_missing() => {
+                                    ::std::result::Result::Ok(#default)
+                                }
+                                res => res,
+                            }
+                        },
+                        None => load,
+                    }
+                }
+            };
+            let load = if self.file {
+                quote! {
+                    #from_env::FileIndirect::resolve(__from_env_source, &__from_env_var)
+                        .and_then(|__from_env_file| #load)
+                }
+            } else {
+                load
+            };
+            let load = if let Some(validate) = &self.validate {
+                quote! {
+                    #load.and_then(|__from_env_value| match #validate(&__from_env_value) {
+                        ::std::result::Result::Ok(()) => ::std::result::Result::Ok(__from_env_value),
+                        ::std::result::Result::Err(reason) => ::std::result::Result::Err(
+                            #from_env::FromEnvErr::invalid(&[&__from_env_var], reason)
+                        ),
+                    })
+                }
+            } else {
+                load
+            };
+            let aliases = if self.aliases.is_empty() {
+                quote! {}
+            } else {
+                let aliases = &self.aliases;
+                quote! {
+                    let __from_env_aliased = #from_env::Aliased::new(
+                        __from_env_source,
+                        &*__from_env_var,
+                        [#(#from_env::prefixed_var(__from_env_prefix, #aliases)),*],
+                    );
+                    let __from_env_source: &dyn #from_env::EnvSource = &__from_env_aliased;
+                }
+            };
+            quote! {
+                {
+                    let __from_env_var = #from_env::prefixed_var(__from_env_prefix, #env_var);
+                    #aliases
+                    #load
+                }
+            }
         } else {
-            quote! { FromEnv::from_env() }
+            let prefix = self.prefix.as_ref().map(LitStr::value).unwrap_or_default();
+            quote! {
+                #from_env::FromEnv::from_source_with_prefix(
+                    __from_env_source,
+                    &#from_env::prefixed_var(__from_env_prefix, #prefix),
+                )
+            }
         };
 
         if self.infallible {
-            quote! {
-                let #field_name = #fn_invoc
-                    .map_err(FromEnvErr::infallible_into)?;
-            }
+            quote! { #fn_invoc.map_err(#from_env::FromEnvErr::infallible_into) }
         } else {
-            quote! {
-                let #field_name = #fn_invoc?;
+            fn_invoc
+        }
+    }
+
+    pub(crate) fn expand_item_from_env(&self, from_env: &TokenStream, idx: usize) -> TokenStream {
+        let field_name = self.field_name(idx);
+
+        if self.skip {
+            return quote! {
+                let #field_name = ::std::default::Default::default();
+            };
+        }
+
+        let load = self.expand_load(from_env);
+        quote! {
+            let #field_name = #load?;
+        }
+    }
+
+    /// Produces a line for the `from_source_accumulate_with_prefix` function.
+    /// The field is loaded as an `Option`, with any errors recorded in
+    /// `__from_env_errors`.
+    pub(crate) fn expand_item_accumulate(&self, from_env: &TokenStream, idx: usize) -> TokenStream {
+        let field_name = self.field_name(idx);
+
+        if self.skip {
+            return quote! {
+                let #field_name = ::std::option::Option::Some(::std::default::Default::default());
+            };
+        }
+
+        if self.env_var.is_none() {
+            let prefix = self.prefix.as_ref().map(LitStr::value).unwrap_or_default();
+            return quote! {
+                let #field_name = __from_env_errors.collect_all(
+                    #from_env::FromEnv::from_source_accumulate_with_prefix(
+                        __from_env_source,
+                        &#from_env::prefixed_var(__from_env_prefix, #prefix),
+                    )
+                );
+            };
+        }
+
+        let load = self.expand_load(from_env);
+        quote! {
+            let #field_name = __from_env_errors.collect(#load);
+        }
+    }
+}
+
+/// True if the tokens mention any of the `params`.
+fn mentions(tokens: TokenStream, params: &[Ident]) -> bool {
+    tokens.into_iter().any(|tt| match tt {
+        TokenTree::Ident(ident) => params.contains(&ident),
+        TokenTree::Group(group) => mentions(group.stream(), params),
+        _ => false,
+    })
+}
+
+/// The default as shown in the inventory. String literals are shown as their
+/// value, other expressions as their source.
+fn display_default(default: &Expr) -> String {
+    match default {
+        Expr::Lit(ExprLit {
+            lit: Lit::Str(s), ..
+        }) => s.value(),
+        _ => display_tokens(default),
+    }
+}
+
+/// Render tokens as source, without the spaces that `quote` inserts between
+/// punctuation, e.g. `Option<String>` rather than `Option < String >`.
+fn display_tokens(tokens: &impl ToTokens) -> String {
+    let raw = tokens.to_token_stream().to_string();
+    let is_word = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric() || c == '_');
+
+    let mut out = String::with_capacity(raw.len());
+    let mut chars = raw.chars().peekable();
+    let mut in_str = false;
+    while let Some(c) = chars.next() {
+        if in_str {
+            out.push(c);
+            match c {
+                '\\' => out.extend(chars.next()),
+                '"' => in_str = false,
+                _ => {}
             }
+            continue;
+        }
+        if c == '"' {
+            in_str = true;
+        } else if c == ' ' {
+            // Keep spaces only where they separate two words, e.g. in
+            // `&'static str` or `dyn Trait`.
+            if is_word(out.chars().last()) && is_word(chars.peek().copied()) {
+                out.push(' ');
+            }
+            continue;
+        }
+        out.push(c);
+        if matches!(c, ',' | ';') {
+            out.push(' ');
         }
     }
+    out
 }
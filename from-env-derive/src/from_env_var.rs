@@ -0,0 +1,202 @@
+use crate::variant::snake_case;
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{DeriveInput, LitStr};
+
+/// A parsed input to `#[derive(FromEnvVar)]`.
+pub(crate) struct Input {
+    ident: syn::Ident,
+
+    generics: syn::Generics,
+
+    body: Body,
+
+    crate_name: syn::Path,
+}
+
+enum Body {
+    /// A unit-only enum, with the accepted value and name of each variant.
+    Enum(Vec<(String, syn::Ident)>),
+    /// A single-field struct, with the field name if any, and its type.
+    Newtype(Option<syn::Ident>, Box<syn::Type>),
+}
+
+impl TryFrom<&DeriveInput> for Input {
+    type Error = syn::Error;
+
+    fn try_from(input: &DeriveInput) -> Result<Self, syn::Error> {
+        let mut crate_name = None;
+        for attr in input
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("from_env_var"))
+        {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("crate") {
+                    crate_name = Some(crate::crate_path(&meta)?);
+                    return Ok(());
+                }
+                Err(meta.error("unknown from_env_var attribute, expected `crate`"))
+            })?;
+        }
+        let crate_name =
+            crate_name.unwrap_or_else(|| syn::parse_str::<syn::Path>("::init4_bin_base").unwrap());
+
+        let body = match &input.data {
+            syn::Data::Enum(data) => {
+                let variants = data
+                    .variants
+                    .iter()
+                    .map(|variant| {
+                        if !matches!(variant.fields, syn::Fields::Unit) {
+                            return Err(syn::Error::new_spanned(
+                                variant,
+                                "FromEnvVar can only be derived for enums with unit variants",
+                            ));
+                        }
+                        Ok((
+                            rename(&variant.attrs, &variant.ident)?,
+                            variant.ident.clone(),
+                        ))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                if variants.is_empty() {
+                    return Err(syn::Error::new(
+                        input.ident.span(),
+                        "FromEnvVar can only be derived for enums with variants",
+                    ));
+                }
+                for (i, (value, _)) in variants.iter().enumerate() {
+                    if variants[..i]
+                        .iter()
+                        .any(|(other, _)| other.eq_ignore_ascii_case(value))
+                    {
+                        return Err(syn::Error::new(
+                            input.ident.span(),
+                            format!("duplicate value `{value}`"),
+                        ));
+                    }
+                }
+
+                Body::Enum(variants)
+            }
+            syn::Data::Struct(data) if data.fields.len() == 1 => {
+                let field = data.fields.iter().next().unwrap();
+                Body::Newtype(field.ident.clone(), Box::new(field.ty.clone()))
+            }
+            _ => {
+                return Err(syn::Error::new(
+                    input.ident.span(),
+                    "FromEnvVar can only be derived for unit-only enums and single-field structs",
+                ));
+            }
+        };
+
+        Ok(Input {
+            ident: input.ident.clone(),
+            generics: input.generics.clone(),
+            body,
+            crate_name,
+        })
+    }
+}
+
+impl Input {
+    pub(crate) fn expand(&self) -> TokenStream {
+        let crate_name = &self.crate_name;
+        let from_env = quote! { #crate_name::utils::from_env };
+        let ident = &self.ident;
+        let (impl_generics, ty_generics, where_clause) = self.generics.split_for_impl();
+
+        match &self.body {
+            Body::Enum(variants) => {
+                let values = variants.iter().map(|(value, _)| value);
+                let idx = 0..variants.len();
+                let idents = variants.iter().map(|(_, ident)| ident);
+
+                quote! {
+                    #[automatically_derived]
+                    impl #impl_generics #from_env::FromEnvVar for #ident #ty_generics #where_clause {
+                        const VALUES: &'static [&'static str] = &[#(#values),*];
+
+                        fn from_env_var_in(
+                            __from_env_source: &dyn #from_env::EnvSource,
+                            __from_env_var: &str,
+                        ) -> ::std::result::Result<Self, #from_env::FromEnvErr> {
+                            match #from_env::parse_one_of(__from_env_source, __from_env_var, Self::VALUES)? {
+                                #(
+                                    #idx => ::std::result::Result::Ok(Self::#idents),
+                                )*
+                                _ => ::std::unreachable!(),
+                            }
+                        }
+                    }
+                }
+            }
+            Body::Newtype(field_name, field_ty) => {
+                let instantiate = match field_name {
+                    Some(field_name) => {
+                        quote! { |__from_env_value| Self { #field_name: __from_env_value } }
+                    }
+                    None => quote! { Self },
+                };
+
+                let mut generics = self.generics.clone();
+                generics
+                    .make_where_clause()
+                    .predicates
+                    .push(syn::parse_quote! { #field_ty: #from_env::FromEnvVar });
+                let (_, _, where_clause) = generics.split_for_impl();
+
+                quote! {
+                    #[automatically_derived]
+                    impl #impl_generics #from_env::FromEnvVar for #ident #ty_generics #where_clause {
+                        const VALUES: &'static [&'static str] = <#field_ty as #from_env::FromEnvVar>::VALUES;
+                        const SECRET: bool = <#field_ty as #from_env::FromEnvVar>::SECRET;
+
+                        fn from_env_var_in(
+                            __from_env_source: &dyn #from_env::EnvSource,
+                            __from_env_var: &str,
+                        ) -> ::std::result::Result<Self, #from_env::FromEnvErr> {
+                            <#field_ty as #from_env::FromEnvVar>::from_env_var_in(__from_env_source, __from_env_var)
+                                .map(#instantiate)
+                        }
+
+                        fn from_env_var_in_with_separator(
+                            __from_env_source: &dyn #from_env::EnvSource,
+                            __from_env_var: &str,
+                            __from_env_separator: &str,
+                        ) -> ::std::result::Result<Self, #from_env::FromEnvErr> {
+                            <#field_ty as #from_env::FromEnvVar>::from_env_var_in_with_separator(
+                                __from_env_source,
+                                __from_env_var,
+                                __from_env_separator,
+                            )
+                            .map(#instantiate)
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The value accepted for a variant, from `#[from_env_var(rename = "...")]`,
+/// or the `snake_case` variant name.
+fn rename(attrs: &[syn::Attribute], ident: &syn::Ident) -> Result<String, syn::Error> {
+    let mut renamed = None;
+    for attr in attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("from_env_var"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                renamed = Some(meta.value()?.parse::<LitStr>()?.value());
+                return Ok(());
+            }
+            Err(meta.error("unknown variant attribute, expected `rename`"))
+        })?;
+    }
+    Ok(renamed.unwrap_or_else(|| snake_case(&ident.to_string())))
+}
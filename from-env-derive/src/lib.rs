@@ -1,93 +1,244 @@
 use proc_macro::TokenStream as Ts;
 use proc_macro2::TokenStream;
 use quote::quote;
-use syn::{parse_macro_input, DeriveInput};
+use syn::{parse_macro_input, DeriveInput, LitStr};
 
 mod field;
 use field::Field;
 
-/// This macro generates an implementation of the `FromEnv` trait for a struct.
+mod variant;
+use variant::Variant;
+
+mod from_env_var;
+
+/// This macro generates an implementation of the `FromEnv` trait for a struct,
+/// or for an enum selected by a tag var.
 /// See the documenetation in init4_bin_base for more details.
 #[proc_macro_derive(FromEnv, attributes(from_env))]
 pub fn derive(input: Ts) -> Ts {
     let input = parse_macro_input!(input as DeriveInput);
 
-    if !matches!(input.data, syn::Data::Struct(_)) {
-        syn::Error::new(
-            input.ident.span(),
-            "FromEnv can only be derived for structs",
-        )
-        .to_compile_error();
-    };
-
-    let syn::Data::Struct(data) = &input.data else {
-        unreachable!()
-    };
-
-    let crate_name = input
-        .attrs
-        .iter()
-        .find(|attr| attr.path().is_ident("from_env"))
-        .and_then(|attr| attr.parse_args::<syn::Path>().ok())
-        .unwrap_or_else(|| syn::parse_str::<syn::Path>("::init4_bin_base").unwrap());
-
-    let tuple_like = matches!(data.fields, syn::Fields::Unnamed(_));
-
-    if matches!(data.fields, syn::Fields::Unit) {
-        syn::Error::new(
-            input.ident.span(),
-            "FromEnv can only be derived for structs with fields",
-        )
-        .to_compile_error();
-    }
-
-    let fields = match &data.fields {
-        syn::Fields::Named(fields) => fields.named.iter().map(Field::try_from),
-        syn::Fields::Unnamed(fields) => fields.unnamed.iter().map(Field::try_from),
-        syn::Fields::Unit => unreachable!(),
-    };
-
-    let fields = match fields.collect::<Result<Vec<_>, _>>() {
-        Ok(fields) => fields,
-        Err(err) => {
-            return err.to_compile_error().into();
-        }
-    };
+    match Input::try_from(&input) {
+        Ok(input) => input.expand().into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+/// This macro generates an implementation of the `FromEnvVar` trait for a
+/// unit-only enum, or for a single-field struct.
+/// See the documenetation in init4_bin_base for more details.
+#[proc_macro_derive(FromEnvVar, attributes(from_env_var))]
+pub fn derive_from_env_var(input: Ts) -> Ts {
+    let input = parse_macro_input!(input as DeriveInput);
 
-    let input = Input {
-        ident: input.ident.clone(),
-        fields,
-        crate_name,
-        tuple_like,
-    };
+    match from_env_var::Input::try_from(&input) {
+        Ok(input) => input.expand().into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
 
-    input.expand_mod().into()
+/// Parse the path of `init4_bin_base` from a `crate` attribute. The bare
+/// `crate` is used within `init4_bin_base` itself, and `crate = "path"` names
+/// the crate when it is re-exported elsewhere.
+pub(crate) fn crate_path(meta: &syn::meta::ParseNestedMeta<'_>) -> syn::Result<syn::Path> {
+    if meta.input.peek(syn::Token![=]) {
+        meta.value()?.parse::<LitStr>()?.parse()
+    } else {
+        Ok(meta.path.clone())
+    }
 }
 
 struct Input {
     ident: syn::Ident,
 
-    fields: Vec<Field>,
+    generics: syn::Generics,
+
+    body: Body,
 
     crate_name: syn::Path,
 
-    tuple_like: bool,
+    deserialize: bool,
+
+    validate_with: Option<syn::Path>,
+}
+
+enum Body {
+    Struct {
+        fields: Vec<Field>,
+
+        tuple_like: bool,
+    },
+    Enum {
+        tag_var: LitStr,
+
+        desc: String,
+
+        variants: Vec<Variant>,
+    },
+}
+
+impl TryFrom<&DeriveInput> for Input {
+    type Error = syn::Error;
+
+    fn try_from(input: &DeriveInput) -> Result<Self, syn::Error> {
+        let mut crate_name = None;
+        let mut deserialize = false;
+        let mut validate_with = None;
+        let mut tag_var = None;
+        let mut desc = None;
+        for attr in input
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("from_env"))
+        {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("crate") {
+                    crate_name = Some(crate_path(&meta)?);
+                } else if meta.path.is_ident("deserialize") {
+                    deserialize = true;
+                } else if meta.path.is_ident("validate_with") {
+                    validate_with = Some(meta.value()?.parse::<syn::Path>()?);
+                } else if meta.path.is_ident("tag") {
+                    tag_var = Some(meta.value()?.parse::<LitStr>()?);
+                } else if meta.path.is_ident("desc") {
+                    desc = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else {
+                    return Err(meta.error("unknown from_env attribute"));
+                }
+                Ok(())
+            })?;
+        }
+        let crate_name =
+            crate_name.unwrap_or_else(|| syn::parse_str::<syn::Path>("::init4_bin_base").unwrap());
+
+        let params: Vec<syn::Ident> = input
+            .generics
+            .type_params()
+            .map(|param| param.ident.clone())
+            .chain(
+                input
+                    .generics
+                    .const_params()
+                    .map(|param| param.ident.clone()),
+            )
+            .collect();
+
+        let body = match &input.data {
+            syn::Data::Struct(data) => {
+                if tag_var.is_some() {
+                    return Err(syn::Error::new(
+                        input.ident.span(),
+                        "`tag` applies to enums",
+                    ));
+                }
+
+                let tuple_like = matches!(data.fields, syn::Fields::Unnamed(_));
+
+                if matches!(data.fields, syn::Fields::Unit) {
+                    return Err(syn::Error::new(
+                        input.ident.span(),
+                        "FromEnv can only be derived for structs with fields",
+                    ));
+                }
+
+                let mut fields = data
+                    .fields
+                    .iter()
+                    .map(Field::try_from)
+                    .collect::<Result<Vec<_>, _>>()?;
+                fields
+                    .iter_mut()
+                    .for_each(|field| field.mark_generic(&params));
+
+                Body::Struct { fields, tuple_like }
+            }
+            syn::Data::Enum(data) => {
+                let Some(tag_var) = tag_var else {
+                    return Err(syn::Error::new(
+                        input.ident.span(),
+                        "Missing tag for enum. Use `#[from_env(tag = \"VAR\", desc = \"DESC\")]`",
+                    ));
+                };
+                let Some(desc) = desc else {
+                    return Err(syn::Error::new(
+                        input.ident.span(),
+                        "Missing description for tag. Use `#[from_env(desc = \"DESC\")]`",
+                    ));
+                };
+
+                let mut variants = data
+                    .variants
+                    .iter()
+                    .map(Variant::try_from)
+                    .collect::<Result<Vec<_>, _>>()?;
+                variants
+                    .iter_mut()
+                    .for_each(|variant| variant.mark_generic(&params));
+
+                if variants.is_empty() {
+                    return Err(syn::Error::new(
+                        input.ident.span(),
+                        "FromEnv can only be derived for enums with variants",
+                    ));
+                }
+                for (i, variant) in variants.iter().enumerate() {
+                    if variants[..i]
+                        .iter()
+                        .any(|v| v.tag().eq_ignore_ascii_case(variant.tag()))
+                    {
+                        return Err(syn::Error::new(
+                            input.ident.span(),
+                            format!("duplicate tag value `{}`", variant.tag()),
+                        ));
+                    }
+                }
+
+                Body::Enum {
+                    tag_var,
+                    desc,
+                    variants,
+                }
+            }
+            syn::Data::Union(_) => {
+                return Err(syn::Error::new(
+                    input.ident.span(),
+                    "FromEnv can only be derived for structs and enums",
+                ));
+            }
+        };
+
+        Ok(Input {
+            ident: input.ident.clone(),
+            generics: input.generics.clone(),
+            body,
+            crate_name,
+            deserialize,
+            validate_with,
+        })
+    }
 }
 
 impl Input {
-    fn field_names(&self) -> Vec<syn::Ident> {
-        self.fields
+    /// The path of the `from_env` module. Every item in the generated code
+    /// is fully qualified, so that it cannot clash with the caller's types.
+    fn module_path(&self) -> TokenStream {
+        let crate_name = &self.crate_name;
+        quote! { #crate_name::utils::from_env }
+    }
+
+    fn field_names(fields: &[Field]) -> Vec<syn::Ident> {
+        fields
             .iter()
             .enumerate()
             .map(|(idx, field)| field.field_name(idx))
             .collect()
     }
 
-    fn instantiate_struct(&self) -> TokenStream {
+    fn instantiate_struct(&self, fields: &[Field], tuple_like: bool) -> TokenStream {
         let struct_name = &self.ident;
-        let field_names = self.field_names();
+        let field_names = Self::field_names(fields);
 
-        if self.tuple_like {
+        if tuple_like {
             return quote! {
                 #struct_name(
                     #(#field_names),*
@@ -102,32 +253,107 @@ impl Input {
         }
     }
 
-    fn item_from_envs(&self) -> Vec<TokenStream> {
-        self.fields
+    fn item_from_envs(&self, fields: &[Field]) -> Vec<TokenStream> {
+        let from_env = self.module_path();
+        fields
             .iter()
             .enumerate()
-            .map(|(idx, field)| field.expand_item_from_env(idx))
+            .map(|(idx, field)| field.expand_item_from_env(&from_env, idx))
             .collect()
     }
 
-    fn env_item_info(&self) -> Vec<TokenStream> {
-        self.fields
+    fn item_accumulates(&self, fields: &[Field]) -> Vec<TokenStream> {
+        let from_env = self.module_path();
+        fields
             .iter()
-            .map(|field| field.expand_env_item_info())
+            .enumerate()
+            .map(|(idx, field)| field.expand_item_accumulate(&from_env, idx))
             .collect()
     }
 
+    fn effectives(&self, fields: &[Field]) -> Vec<TokenStream> {
+        let from_env = self.module_path();
+        fields
+            .iter()
+            .enumerate()
+            .map(|(idx, field)| field.expand_effective(&from_env, idx, &field.config_key(idx)))
+            .collect()
+    }
+
+    fn env_item_info(&self, fields: &[Field]) -> Vec<TokenStream> {
+        let from_env = self.module_path();
+        let group = self.ident.to_string();
+        fields
+            .iter()
+            .enumerate()
+            .map(|(idx, field)| {
+                field.expand_env_item_info(&from_env, &field.config_key(idx), &group)
+            })
+            .collect()
+    }
+
+    /// The generics of the impl, with the bounds required to load each
+    /// generic field added to the where clause. Type parameters must be
+    /// `'static`, as required by `FromEnv`.
+    fn impl_generics(&self) -> syn::Generics {
+        let mut generics = self.generics.clone();
+
+        let fields: Vec<&Field> = match &self.body {
+            Body::Struct { fields, .. } => fields.iter().collect(),
+            Body::Enum { variants, .. } => variants.iter().flat_map(Variant::fields).collect(),
+        };
+        let static_bounds = self.generics.type_params().map(|param| {
+            let ident = &param.ident;
+            quote! { #ident: 'static }
+        });
+        let from_env = self.module_path();
+        let field_bounds = fields
+            .into_iter()
+            .filter_map(|field| field.bound(&from_env));
+
+        let where_clause = generics.make_where_clause();
+        for bound in static_bounds.chain(field_bounds) {
+            where_clause.predicates.push(syn::parse_quote! { #bound });
+        }
+        generics
+    }
+
     fn expand_impl(&self) -> TokenStream {
-        let env_item_info = self.env_item_info();
+        match &self.body {
+            Body::Struct { fields, tuple_like } => self.expand_struct_impl(fields, *tuple_like),
+            Body::Enum {
+                tag_var,
+                desc,
+                variants,
+            } => self.expand_enum_impl(tag_var, desc, variants),
+        }
+    }
+
+    fn expand_struct_impl(&self, fields: &[Field], tuple_like: bool) -> TokenStream {
+        let from_env = self.module_path();
+        let env_item_info = self.env_item_info(fields);
         let struct_name = &self.ident;
+        let generics = self.impl_generics();
+        let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
 
-        let item_from_envs = self.item_from_envs();
-        let struct_instantiation = self.instantiate_struct();
+        let item_from_envs = self.item_from_envs(fields);
+        let item_accumulates = self.item_accumulates(fields);
+        let field_names = Self::field_names(fields);
+        let struct_instantiation = self.instantiate_struct(fields, tuple_like);
+        let struct_pattern = if tuple_like {
+            quote! { Self(#(#field_names),*) }
+        } else {
+            quote! { Self { #(#field_names),* } }
+        };
+        let effectives = self.effectives(fields);
+        let group = struct_name.to_string();
+        let validate = self.expand_validate();
+        let validate_accumulate = self.expand_validate_accumulate();
 
         quote! {
             #[automatically_derived]
-            impl FromEnv for #struct_name {
-                fn inventory() -> ::std::vec::Vec<&'static EnvItemInfo> {
+            impl #impl_generics #from_env::FromEnv for #struct_name #ty_generics #where_clause {
+                fn inventory() -> ::std::vec::Vec<&'static #from_env::EnvItemInfo> {
                     let mut items = ::std::vec::Vec::new();
                     #(
                         #env_item_info
@@ -135,31 +361,245 @@ impl Input {
                     items
                 }
 
-                fn from_env() -> ::std::result::Result<Self, FromEnvErr> {
+                fn from_source_with_prefix(
+                    __from_env_source: &dyn #from_env::EnvSource,
+                    __from_env_prefix: &str,
+                ) -> ::std::result::Result<Self, #from_env::FromEnvErr> {
                     #(
                         #item_from_envs
                     )*
 
-                    ::std::result::Result::Ok(#struct_instantiation)
+                    let __from_env_value = #struct_instantiation;
+                    #validate
+                    ::std::result::Result::Ok(__from_env_value)
+                }
+
+                fn from_source_accumulate_with_prefix(
+                    __from_env_source: &dyn #from_env::EnvSource,
+                    __from_env_prefix: &str,
+                ) -> ::std::result::Result<Self, #from_env::FromEnvErrors> {
+                    let mut __from_env_errors = #from_env::FromEnvErrors::new();
+
+                    #(
+                        #item_accumulates
+                    )*
+
+                    let (#(::std::option::Option::Some(#field_names),)*) = (#(#field_names,)*) else {
+                        return ::std::result::Result::Err(
+                            __from_env_errors.with_inventory(
+                                &Self::inventory_with_prefix(__from_env_prefix)
+                            )
+                        );
+                    };
+
+                    let __from_env_value = #struct_instantiation;
+                    #validate_accumulate
+                    ::std::result::Result::Ok(__from_env_value)
+                }
+
+                #[allow(unused_variables)]
+                fn effective_config_with_prefix(
+                    &self,
+                    __from_env_source: &dyn #from_env::EnvSource,
+                    __from_env_prefix: &str,
+                ) -> #from_env::EffectiveConfig {
+                    let __from_env_items = Self::inventory_with_prefix(__from_env_prefix);
+                    let mut __from_env_config = #from_env::EffectiveConfig::new(#group);
+                    let #struct_pattern = self;
+                    #(
+                        #effectives
+                    )*
+                    __from_env_config
                 }
             }
         }
     }
 
-    fn expand_mod(&self) -> TokenStream {
-        let expanded_impl = self.expand_impl();
-        let crate_name = &self.crate_name;
+    /// Produces the impl for an enum. The tag var is loaded first, and
+    /// selects the variant whose fields are then loaded. Tags are matched
+    /// ignoring ASCII case, as for `#[derive(FromEnvVar)]` enums.
+    fn expand_enum_impl(&self, tag_var: &LitStr, desc: &str, variants: &[Variant]) -> TokenStream {
+        let from_env = self.module_path();
+        let enum_name = &self.ident;
+        let group = enum_name.to_string();
+        let generics = self.impl_generics();
+        let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+        let tags = variants
+            .iter()
+            .map(|variant| format!("`{}`", variant.tag()))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let tag_values = variants.iter().map(Variant::tag);
+        let unknown_tag = format!("expected one of {tags}");
+        let key = tag_var.value().to_lowercase();
 
-        let mod_ident =
-            syn::parse_str::<syn::Ident>(&format!("__from_env_impls_{}", self.ident)).unwrap();
+        let env_item_info = variants
+            .iter()
+            .map(|variant| variant.expand_env_item_info(&from_env, &tag_var.value(), &group));
+        let arms = variants.iter().map(|variant| variant.expand_arm(&from_env));
+        let arms_accumulate = variants
+            .iter()
+            .map(|variant| variant.expand_arm_accumulate(&from_env));
+        let arms_effective = variants
+            .iter()
+            .map(|variant| variant.expand_arm_effective(&from_env));
+        let validate = self.expand_validate();
+        let validate_accumulate = self.expand_validate_accumulate();
 
         quote! {
-            mod #mod_ident {
-                use super::*;
-                use #crate_name::utils::from_env::{FromEnv, FromEnvErr, FromEnvVar, EnvItemInfo};
+            #[automatically_derived]
+            impl #impl_generics #from_env::FromEnv for #enum_name #ty_generics #where_clause {
+                fn inventory() -> ::std::vec::Vec<&'static #from_env::EnvItemInfo> {
+                    let mut items = ::std::vec::Vec::new();
+                    items.push(&const {
+                        #from_env::EnvItemInfo::new(#tag_var, #desc)
+                            .type_name(#group)
+                            .group(#group)
+                            .key(#key)
+                            .values(&[#(#tag_values),*])
+                    });
+                    #(
+                        #env_item_info
+                    )*
+                    items
+                }
+
+                fn from_source_with_prefix(
+                    __from_env_source: &dyn #from_env::EnvSource,
+                    __from_env_prefix: &str,
+                ) -> ::std::result::Result<Self, #from_env::FromEnvErr> {
+                    let __from_env_tag_var = #from_env::prefixed_var(__from_env_prefix, #tag_var);
+                    let __from_env_tag: ::std::string::String =
+                        #from_env::FromEnvVar::from_env_var_in(__from_env_source, &__from_env_tag_var)?;
 
-                #expanded_impl
+                    let __from_env_value = match () {
+                        #(
+                            #arms
+                        )*
+                        _ => return ::std::result::Result::Err(
+                            #from_env::FromEnvErr::invalid(&[&__from_env_tag_var], #unknown_tag)
+                        ),
+                    };
+                    #validate
+                    ::std::result::Result::Ok(__from_env_value)
+                }
+
+                fn from_source_accumulate_with_prefix(
+                    __from_env_source: &dyn #from_env::EnvSource,
+                    __from_env_prefix: &str,
+                ) -> ::std::result::Result<Self, #from_env::FromEnvErrors> {
+                    let __from_env_tag_var = #from_env::prefixed_var(__from_env_prefix, #tag_var);
+                    let __from_env_tag: ::std::string::String =
+                        #from_env::FromEnvVar::from_env_var_in(__from_env_source, &__from_env_tag_var)
+                            .map_err(|err| #from_env::FromEnvErrors::from_err(
+                                err,
+                                &Self::inventory_with_prefix(__from_env_prefix),
+                            ))?;
+
+                    let __from_env_value = match () {
+                        #(
+                            #arms_accumulate
+                        )*
+                        _ => return ::std::result::Result::Err(
+                            #from_env::FromEnvErrors::from_err(
+                                #from_env::FromEnvErr::invalid(&[&__from_env_tag_var], #unknown_tag),
+                                &Self::inventory_with_prefix(__from_env_prefix),
+                            )
+                        ),
+                    };
+                    #validate_accumulate
+                    ::std::result::Result::Ok(__from_env_value)
+                }
+
+                #[allow(unused_variables)]
+                fn effective_config_with_prefix(
+                    &self,
+                    __from_env_source: &dyn #from_env::EnvSource,
+                    __from_env_prefix: &str,
+                ) -> #from_env::EffectiveConfig {
+                    let __from_env_items = Self::inventory_with_prefix(__from_env_prefix);
+                    let __from_env_tag_var = #from_env::prefixed_var(__from_env_prefix, #tag_var);
+                    let mut __from_env_config = #from_env::EffectiveConfig::new(#group);
+                    match self {
+                        #(
+                            #arms_effective
+                        )*
+                    }
+                    __from_env_config
+                }
+            }
+        }
+    }
+
+    /// Produces the call to the `validate_with` hook in
+    /// `from_source_with_prefix`. Errors are reported with prefixed var names.
+    fn expand_validate(&self) -> TokenStream {
+        let Some(validate_with) = &self.validate_with else {
+            return quote! {};
+        };
+        quote! {
+            #validate_with(&__from_env_value)
+                .map_err(|err| err.with_prefix(__from_env_prefix))?;
+        }
+    }
+
+    /// Produces the call to the `validate_with` hook in
+    /// `from_source_accumulate_with_prefix`.
+    fn expand_validate_accumulate(&self) -> TokenStream {
+        let Some(validate_with) = &self.validate_with else {
+            return quote! {};
+        };
+        let from_env = self.module_path();
+        quote! {
+            if let ::std::result::Result::Err(err) = #validate_with(&__from_env_value) {
+                return ::std::result::Result::Err(
+                    #from_env::FromEnvErrors::from_err(
+                        err.with_prefix(__from_env_prefix),
+                        &Self::inventory_with_prefix(__from_env_prefix),
+                    )
+                );
             }
         }
     }
+
+    /// Produces a `serde::Deserialize` impl that loads the struct from a
+    /// config file value, using the config keys in the inventory.
+    fn expand_deserialize(&self) -> TokenStream {
+        if !self.deserialize {
+            return quote! {};
+        }
+
+        let struct_name = &self.ident;
+        let crate_name = &self.crate_name;
+        let from_env = self.module_path();
+
+        let mut generics = self.impl_generics();
+        generics.params.insert(0, syn::parse_quote! { 'de });
+        let (impl_generics, _, where_clause) = generics.split_for_impl();
+        let (_, ty_generics, _) = self.generics.split_for_impl();
+
+        quote! {
+            #[automatically_derived]
+            impl #impl_generics #crate_name::deps::serde::Deserialize<'de> for #struct_name #ty_generics #where_clause {
+                fn deserialize<D>(deserializer: D) -> ::std::result::Result<Self, D::Error>
+                where
+                    D: #crate_name::deps::serde::Deserializer<'de>,
+                {
+                    #from_env::ConfigFile::deserialize_config(deserializer)
+                }
+            }
+        }
+    }
+
+    fn expand(&self) -> TokenStream {
+        let expanded_impl = self.expand_impl();
+        let expanded_deserialize = self.expand_deserialize();
+
+        quote! {
+            #expanded_impl
+
+            #expanded_deserialize
+        }
+    }
 }
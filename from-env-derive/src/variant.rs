@@ -0,0 +1,240 @@
+use crate::field::Field;
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Ident, LitStr};
+
+/// A parsed variant of an enum, selected by the value of the enum's tag var.
+pub(crate) struct Variant {
+    ident: Ident,
+    tag: String,
+    fields: Vec<Field>,
+    tuple_like: bool,
+}
+
+impl TryFrom<&syn::Variant> for Variant {
+    type Error = syn::Error;
+
+    fn try_from(variant: &syn::Variant) -> Result<Self, syn::Error> {
+        let mut tag = None;
+
+        for attr in variant
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("from_env"))
+        {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("tag") {
+                    tag = Some(meta.value()?.parse::<LitStr>()?.value());
+                    return Ok(());
+                }
+                Err(meta.error("unknown variant attribute, expected `tag`"))
+            })?;
+        }
+
+        let fields = variant
+            .fields
+            .iter()
+            .map(Field::try_from)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Variant {
+            tag: tag.unwrap_or_else(|| snake_case(&variant.ident.to_string())),
+            ident: variant.ident.clone(),
+            fields,
+            tuple_like: matches!(variant.fields, syn::Fields::Unnamed(_)),
+        })
+    }
+}
+
+impl Variant {
+    /// Record which fields mention the generic `params` of the enum.
+    pub(crate) fn mark_generic(&mut self, params: &[Ident]) {
+        self.fields
+            .iter_mut()
+            .for_each(|field| field.mark_generic(params));
+    }
+
+    /// The fields of the variant.
+    pub(crate) fn fields(&self) -> &[Field] {
+        &self.fields
+    }
+
+    /// The value of the tag var that selects this variant.
+    pub(crate) fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    fn field_names(&self) -> Vec<Ident> {
+        self.fields
+            .iter()
+            .enumerate()
+            .map(|(idx, field)| field.field_name(idx))
+            .collect()
+    }
+
+    fn instantiate_variant(&self) -> TokenStream {
+        let variant_name = &self.ident;
+        let field_names = self.field_names();
+
+        if self.fields.is_empty() {
+            return quote! { Self::#variant_name };
+        }
+
+        if self.tuple_like {
+            return quote! {
+                Self::#variant_name(
+                    #(#field_names),*
+                )
+            };
+        }
+
+        quote! {
+            Self::#variant_name {
+                #(#field_names),*
+            }
+        }
+    }
+
+    /// Produces the pattern that binds the fields of the variant to their
+    /// field names.
+    fn pattern(&self) -> TokenStream {
+        let variant_name = &self.ident;
+        let field_names = self.field_names();
+
+        if self.fields.is_empty() {
+            return quote! { Self::#variant_name { .. } };
+        }
+        if self.tuple_like {
+            return quote! { Self::#variant_name(#(#field_names),*) };
+        }
+        quote! { Self::#variant_name { #(#field_names),* } }
+    }
+
+    /// Produces the match arm for `effective_config_with_prefix`.
+    pub(crate) fn expand_arm_effective(&self, from_env: &TokenStream) -> TokenStream {
+        let tag = &self.tag;
+        let pattern = self.pattern();
+        let effectives = self
+            .fields
+            .iter()
+            .enumerate()
+            .map(|(idx, field)| field.expand_effective(from_env, idx, &field.variant_key(idx)));
+
+        quote! {
+            #pattern => {
+                if let ::std::option::Option::Some(__from_env_effective) =
+                    #from_env::EffectiveValue::find(&__from_env_items, &__from_env_tag_var, __from_env_source)
+                {
+                    __from_env_config.push_value(__from_env_effective.with_parsed(&#tag));
+                }
+                #(
+                    #effectives
+                )*
+            }
+        }
+    }
+
+    /// Produces the lines for the `inventory` function. Every item is
+    /// conditional on the tag var selecting this variant.
+    pub(crate) fn expand_env_item_info(
+        &self,
+        from_env: &TokenStream,
+        tag_var: &str,
+        group: &str,
+    ) -> TokenStream {
+        let tag = &self.tag;
+        let env_item_info = self.fields.iter().enumerate().map(|(idx, field)| {
+            field.expand_env_item_info(from_env, &field.variant_key(idx), group)
+        });
+
+        quote! {
+            let __from_env_variant: ::std::vec::Vec<&'static #from_env::EnvItemInfo> = {
+                let mut items = ::std::vec::Vec::new();
+                #(
+                    #env_item_info
+                )*
+                items
+            };
+            items.extend(
+                __from_env_variant
+                    .into_iter()
+                    .map(|item| item.with_condition(#tag_var, #tag))
+            );
+        }
+    }
+
+    /// Produces the match arm for `from_source_with_prefix`.
+    pub(crate) fn expand_arm(&self, from_env: &TokenStream) -> TokenStream {
+        let tag = &self.tag;
+        let item_from_envs = self
+            .fields
+            .iter()
+            .enumerate()
+            .map(|(idx, field)| field.expand_item_from_env(from_env, idx));
+        let instantiation = self.instantiate_variant();
+
+        quote! {
+            _ if __from_env_tag.eq_ignore_ascii_case(#tag) => {
+                #(
+                    #item_from_envs
+                )*
+                #instantiation
+            }
+        }
+    }
+
+    /// Produces the match arm for `from_source_accumulate_with_prefix`.
+    pub(crate) fn expand_arm_accumulate(&self, from_env: &TokenStream) -> TokenStream {
+        let tag = &self.tag;
+        let instantiation = self.instantiate_variant();
+
+        if self.fields.is_empty() {
+            return quote! {
+                _ if __from_env_tag.eq_ignore_ascii_case(#tag) => #instantiation,
+            };
+        }
+
+        let item_accumulates = self
+            .fields
+            .iter()
+            .enumerate()
+            .map(|(idx, field)| field.expand_item_accumulate(from_env, idx));
+        let field_names = self.field_names();
+
+        quote! {
+            _ if __from_env_tag.eq_ignore_ascii_case(#tag) => {
+                let mut __from_env_errors = #from_env::FromEnvErrors::new();
+
+                #(
+                    #item_accumulates
+                )*
+
+                let (#(::std::option::Option::Some(#field_names),)*) = (#(#field_names,)*) else {
+                    return ::std::result::Result::Err(
+                        __from_env_errors.with_inventory(
+                            &Self::inventory_with_prefix(__from_env_prefix)
+                        )
+                    );
+                };
+
+                #instantiation
+            }
+        }
+    }
+}
+
+/// Convert a variant name to `snake_case`, e.g. `AwsKms` to `aws_kms`.
+pub(crate) fn snake_case(ident: &str) -> String {
+    let mut out = String::with_capacity(ident.len() + 4);
+    for (i, c) in ident.chars().enumerate() {
+        if c.is_uppercase() {
+            if i != 0 {
+                out.push('_');
+            }
+            out.extend(c.to_lowercase());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
@@ -11,6 +11,14 @@ pub struct InfallibleConfig {
     pub internal_cow: std::borrow::Cow<'static, str>,
 }
 
+// Compile check for an explicit crate path.
+#[derive(Debug, FromEnv)]
+#[from_env(crate = "::init4_bin_base")]
+pub struct ExplicitCrate {
+    #[from_env(var = "EXPLICIT", desc = "A u8")]
+    pub explicit: u8,
+}
+
 #[derive(FromEnv, Debug)]
 pub struct FromEnvTest {
     /// This is a guy named tony
@@ -50,6 +58,33 @@ pub struct Nested {
     pub from_env_test: FromEnvTest,
 }
 
+#[derive(Debug, FromEnv)]
+pub struct Prefixed {
+    #[from_env(var = "PREFIXED_NAME", desc = "The name of the prefixed config")]
+    pub name: String,
+
+    #[from_env(prefix = "HOST_")]
+    pub host: Nested,
+
+    #[from_env(prefix = "RU_")]
+    pub rollup: Nested,
+}
+
+#[derive(Debug, FromEnv)]
+pub struct Accumulating {
+    #[from_env(var = "ACC_U8", desc = "An unparseable u8")]
+    pub bad_u8: u8,
+
+    #[from_env(var = "ACC_MISSING", desc = "A missing u64")]
+    pub missing: u64,
+
+    #[from_env(var = "ACC_OK", desc = "A present string", infallible)]
+    pub ok: String,
+
+    #[from_env(prefix = "ACC_")]
+    pub nested: Nested,
+}
+
 impl FromEnvTest {
     /// Get the memoized value
     pub fn get_memo(&self) -> &str {
@@ -60,30 +95,32 @@ impl FromEnvTest {
 #[cfg(test)]
 mod test {
     use super::*;
-    use init4_bin_base::utils::from_env::{EnvItemInfo, FromEnv};
+    use init4_bin_base::utils::{
+        from_env::{EnvItemInfo, FromEnv, FromEnvErr},
+        test_utils::env_map,
+    };
+    use std::collections::HashMap;
 
     #[test]
     fn load_nested() {
-        unsafe {
-            std::env::set_var("FIELD1", "1");
-            std::env::set_var("FIELD2", "2");
-            std::env::set_var("FIELD3", "3");
-            std::env::set_var("FIELD4", "4");
-            std::env::set_var("FFFFFF", "5");
-        }
+        let mut source = env_map([
+            ("FIELD1", "1"),
+            ("FIELD2", "2"),
+            ("FIELD3", "3"),
+            ("FIELD4", "4"),
+            ("FFFFFF", "5"),
+        ]);
 
-        let nested = Nested::from_env().unwrap();
+        let nested = Nested::from_source(&source).unwrap();
         assert_eq!(nested.from_env_test.tony, 1);
         assert_eq!(nested.from_env_test.charles, 2);
         assert_eq!(nested.from_env_test.patrick, "3");
         assert_eq!(nested.from_env_test.oliver, Some("4".to_string()));
         assert_eq!(nested.ffffff, "5");
 
-        unsafe {
-            std::env::remove_var("FIELD4");
-        }
+        source.remove("FIELD4");
 
-        let nested = Nested::from_env().unwrap();
+        let nested = Nested::from_source(&source).unwrap();
         assert_eq!(nested.from_env_test.tony, 1);
         assert_eq!(nested.from_env_test.charles, 2);
         assert_eq!(nested.from_env_test.patrick, "3");
@@ -91,11 +128,114 @@ mod test {
         assert_eq!(nested.ffffff, "5");
     }
 
+    #[test]
+    fn load_prefixed() {
+        let mut source = env_map([("PREFIXED_NAME", "prefixed")]);
+        for prefix in ["HOST_", "RU_"] {
+            for (i, var) in ["FIELD1", "FIELD2", "FIELD3", "FFFFFF"].iter().enumerate() {
+                let val = if prefix == "HOST_" { i + 10 } else { i + 20 };
+                source.insert(format!("{prefix}{var}"), val.to_string());
+            }
+        }
+
+        let prefixed = Prefixed::from_source(&source).unwrap();
+        assert_eq!(prefixed.name, "prefixed");
+        assert_eq!(prefixed.host.from_env_test.tony, 10);
+        assert_eq!(prefixed.host.from_env_test.charles, 11);
+        assert_eq!(prefixed.host.from_env_test.patrick, "12");
+        assert_eq!(prefixed.host.from_env_test.oliver, None);
+        assert_eq!(prefixed.host.ffffff, "13");
+        assert_eq!(prefixed.rollup.from_env_test.tony, 20);
+        assert_eq!(prefixed.rollup.from_env_test.charles, 21);
+        assert_eq!(prefixed.rollup.from_env_test.patrick, "22");
+        assert_eq!(prefixed.rollup.ffffff, "23");
+
+        // Prefixes compose when the whole struct is loaded with a prefix.
+        let outer: HashMap<_, _> = source
+            .iter()
+            .map(|(var, val)| (format!("OUTER_{var}"), val.clone()))
+            .collect();
+        let prefixed = Prefixed::from_source_with_prefix(&outer, "OUTER_").unwrap();
+        assert_eq!(prefixed.name, "prefixed");
+        assert_eq!(prefixed.host.from_env_test.tony, 10);
+        assert_eq!(prefixed.rollup.ffffff, "23");
+
+        // Unprefixed vars are not read.
+        let err = Prefixed::from_source_with_prefix(&source, "OUTER_").unwrap_err();
+        assert_eq!(err.var(), "OUTER_PREFIXED_NAME");
+        let partial = env_map([("OUTER_PREFIXED_NAME", "outer")]);
+        let err = Prefixed::from_source_with_prefix(&partial, "OUTER_").unwrap_err();
+        assert!(err.var().starts_with("OUTER_HOST_"));
+    }
+
+    #[test]
+    fn prefixed_inventory() {
+        let inv = Prefixed::inventory();
+        assert_eq!(inv.len(), 2 * Nested::inventory().len() + 1);
+        assert!(inv.iter().any(|i| i.var == "PREFIXED_NAME"));
+        assert!(inv.iter().any(|i| i.var == "HOST_FIELD1"));
+        assert!(inv.iter().any(|i| i.var == "RU_FFFFFF"));
+        assert!(!inv.iter().any(|i| i.var == "FIELD1"));
+
+        let inv = Prefixed::inventory_with_prefix("OUTER_");
+        assert!(inv.iter().all(|i| i.var.starts_with("OUTER_")));
+        assert_contains(
+            &inv,
+            &EnvItemInfo::new("OUTER_HOST_FIELD4", "Oliver is an Option<String>")
+                .optional()
+                .type_name("Option<String>")
+                .group("FromEnvTest")
+                .key("host.from_env_test.oliver"),
+        );
+    }
+
+    #[test]
+    fn accumulate_errors() {
+        let mut source = env_map([
+            ("ACC_U8", "300"),
+            ("ACC_OK", "ok"),
+            ("ACC_FIELD1", ""),
+            ("ACC_FIELD2", "not a number"),
+            ("ACC_FIELD3", "3"),
+            ("ACC_FFFFFF", "5"),
+        ]);
+
+        // `from_source` stops at the first error.
+        let err = Accumulating::from_source(&source).unwrap_err();
+        assert_eq!(err.var(), "ACC_U8");
+
+        let errs = Accumulating::from_source_accumulate(&source).unwrap_err();
+        let vars: Vec<_> = errs.iter().map(|(err, _)| err.var().to_owned()).collect();
+        assert_eq!(
+            vars,
+            ["ACC_U8", "ACC_MISSING", "ACC_FIELD1", "ACC_FIELD2"].map(String::from)
+        );
+        assert!(errs
+            .iter()
+            .all(|(err, info)| info.unwrap().var == err.var()));
+        assert!(matches!(
+            errs.iter().nth(1).unwrap(),
+            (FromEnvErr::EnvError(..), Some(info)) if info.description == "A missing u64"
+        ));
+
+        let report = errs.to_string();
+        assert!(report.starts_with("4 errors"));
+        assert!(report.contains("ACC_FIELD2: Charles is a u64"));
+
+        source.extend(env_map([
+            ("ACC_U8", "3"),
+            ("ACC_MISSING", "4"),
+            ("ACC_FIELD1", "1"),
+            ("ACC_FIELD2", "2"),
+        ]));
+        let loaded = Accumulating::from_source_accumulate(&source).unwrap();
+        assert_eq!(loaded.bad_u8, 3);
+        assert_eq!(loaded.nested.from_env_test.charles, 2);
+    }
+
     fn assert_contains(vec: &Vec<&'static EnvItemInfo>, item: &EnvItemInfo) {
         let i = vec.iter().find(|i| i.var == item.var).unwrap();
-        assert_eq!(i.var, item.var);
-        assert_eq!(i.description, item.description);
-        assert_eq!(i.optional, item.optional);
+        assert_eq!(*i, item);
     }
 
     #[test]
@@ -104,49 +244,45 @@ mod test {
         assert_eq!(fet_inv.len(), 4);
         assert_contains(
             &fet_inv,
-            &EnvItemInfo {
-                var: "FIELD1",
-                description: "Tony is cool and a u8",
-                optional: false,
-            },
+            &EnvItemInfo::new("FIELD1", "Tony is cool and a u8")
+                .type_name("u8")
+                .group("FromEnvTest")
+                .key("tony"),
         );
         assert_contains(
             &fet_inv,
-            &EnvItemInfo {
-                var: "FIELD2",
-                description: "Charles is a u64",
-                optional: false,
-            },
+            &EnvItemInfo::new("FIELD2", "Charles is a u64")
+                .type_name("u64")
+                .group("FromEnvTest")
+                .key("charles"),
         );
         assert_contains(
             &fet_inv,
-            &EnvItemInfo {
-                var: "FIELD3",
-                description: "Patrick is a String",
-                optional: false,
-            },
+            &EnvItemInfo::new("FIELD3", "Patrick is a String")
+                .type_name("String")
+                .group("FromEnvTest")
+                .key("patrick"),
         );
         assert_contains(
             &fet_inv,
-            &EnvItemInfo {
-                var: "FIELD4",
-                description: "Oliver is an Option<String>",
-                optional: true,
-            },
+            &EnvItemInfo::new("FIELD4", "Oliver is an Option<String>")
+                .optional()
+                .type_name("Option<String>")
+                .group("FromEnvTest")
+                .key("oliver"),
         );
 
         let nest_inv = Nested::inventory();
         assert_eq!(nest_inv.len(), fet_inv.len() + 1);
         for item in fet_inv {
-            assert_contains(&nest_inv, item);
+            assert_contains(&nest_inv, item.with_key_prefix("from_env_test"));
         }
         assert_contains(
             &nest_inv,
-            &EnvItemInfo {
-                var: "FFFFFF",
-                description: "This is a guy named ffffff",
-                optional: false,
-            },
+            &EnvItemInfo::new("FFFFFF", "This is a guy named ffffff")
+                .type_name("String")
+                .group("Nested")
+                .key("ffffff"),
         );
     }
 }
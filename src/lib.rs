@@ -13,7 +13,7 @@
 #![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
 
 use crate::utils::{
-    from_env::{FromEnv, FromEnvErr},
+    from_env::{ConfigCheck, ConfigFile, EnvDocs, FromEnv, FromEnvErrors},
     metrics::MetricsConfig,
     otlp::OtelGuard,
     tracing::TracingConfig,
@@ -29,6 +29,9 @@ pub mod utils {
     /// slot.
     pub mod calc;
 
+    /// Wall-clock abstractions for deterministic tests.
+    pub mod clock;
+
     /// [`FromEnv`], [`FromEnvVar`] traits and related utilities.
     ///
     /// [`FromEnv`]: from_env::FromEnv
@@ -58,6 +61,14 @@ pub mod utils {
     /// Block watcher utilities.
     #[cfg(feature = "block_watcher")]
     pub mod block_watcher;
+
+    /// Hot-reloadable configuration utilities.
+    #[cfg(feature = "reload")]
+    pub mod reload;
+
+    /// Helpers for testing env-driven configuration.
+    #[cfg(any(test, feature = "test-utils"))]
+    pub mod test_utils;
 }
 
 /// Re-exports of common dependencies.
@@ -66,10 +77,12 @@ pub mod deps {
     pub use opentelemetry;
     pub use opentelemetry_otlp;
     pub use opentelemetry_sdk;
+    pub use serde;
     pub use tracing;
     pub use tracing_core;
     pub use tracing_opentelemetry;
     pub use tracing_subscriber;
+    pub use zeroize;
 }
 
 /// Init metrics and tracing, including OTLP if enabled.
@@ -131,6 +144,17 @@ pub trait Init4Config: FromEnv {
     fn tracing(&self) -> &TracingConfig;
     /// Get the metrics configuration.
     fn metrics(&self) -> &MetricsConfig;
+
+    /// Prefixes of the environment variables that belong to the config, e.g.
+    /// `["OAUTH_"]`. With `--check-config`, set variables that start with one
+    /// of these prefixes, but are not in the inventory, are reported as
+    /// unknown. See [`ConfigCheck`].
+    ///
+    /// If empty, the default, the prefixes are derived from the inventory,
+    /// e.g. `OAUTH_` for `OAUTH_CLIENT_ID`. See
+    /// [`ConfigCheck::with_inventory_prefixes`]. Override this if the
+    /// inventory shares a prefix with unrelated variables, e.g. `AWS_`.
+    const ENV_PREFIXES: &'static [&'static str] = &[];
 }
 
 /// The result of [`init`]: the loaded config and an optional OTLP guard.
@@ -148,24 +172,137 @@ pub struct ConfigAndGuard<T> {
 /// Load config from the environment and initialize metrics and tracing.
 ///
 /// This will perform the following:
-/// - Load `T` from environment variables via [`FromEnv`]
+/// - Load `T` from environment variables via [`FromEnv`], or from the config
+///   file named by `CONFIG_FILE` with environment variables overriding
+///   individual keys (see [`ConfigFile`])
 /// - Read tracing configuration from the loaded config
 /// - Determine whether to enable OTLP
 /// - Install a global tracing subscriber, using the OTLP provider if enabled
 /// - Read metrics configuration from the loaded config
 /// - Install a global metrics recorder and serve it over HTTP on 0.0.0.0
+/// - If `TRACING_LOG_CONFIG` is set, log the redacted
+///   [`EffectiveConfig`] as a single event
+///
+/// If the config cannot be loaded, this prints a report of every missing,
+/// empty or unparseable environment variable to stderr, and exits the process
+/// with a non-zero status code. Use [`try_init`] to handle the errors instead.
+///
+/// Before anything else, this checks the command line arguments for the
+/// following flags. If one is present, the corresponding [`EnvDocs`] for `T`
+/// are printed to stdout and the process exits.
+/// - `--env-help`: Markdown documentation of every environment variable.
+/// - `--print-env-schema`: JSON documentation of every environment variable.
+/// - `--print-json-schema`: a JSON Schema of the environment, see
+///   [`EnvDocs::to_json_schema`].
+/// - `--print-helm-env`: a Kubernetes `env:` snippet for a Helm chart, see
+///   [`EnvDocs::to_helm_env`]. Secrets are read from the Kubernetes secret
+///   named `{binary name}-secrets`.
+///
+/// If `--check-config` is passed, this instead checks the config with a
+/// [`ConfigCheck`], prints the report, and exits with a non-zero status code
+/// if any problems were found.
 ///
 /// See [`init_tracing`] and [`init_metrics`] for more
 /// details on specific actions taken and env vars read.
 ///
 /// [`init_tracing`]: utils::tracing::init_tracing
 /// [`init_metrics`]: utils::metrics::init_metrics
-pub fn init<T: Init4Config>() -> Result<ConfigAndGuard<T>, FromEnvErr> {
-    let config = T::from_env()?;
+/// [`ConfigFile`]: utils::from_env::ConfigFile
+/// [`EffectiveConfig`]: utils::from_env::EffectiveConfig
+pub fn init<T: Init4Config>() -> ConfigAndGuard<T> {
+    handle_env_flags::<T>();
+
+    match try_init() {
+        Ok(config_and_guard) => config_and_guard,
+        Err(errors) => {
+            eprintln!("{errors}");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Print the [`EnvDocs`] for `T` and exit if `--env-help`,
+/// `--print-env-schema`, `--print-json-schema` or `--print-helm-env` was
+/// passed on the command line, or check the config and exit if
+/// `--check-config` was passed.
+fn handle_env_flags<T: Init4Config>() {
+    for arg in std::env::args().skip(1) {
+        let docs = match arg.as_str() {
+            "--env-help" => EnvDocs::of::<T>().to_markdown(),
+            "--print-env-schema" => EnvDocs::of::<T>().to_json(),
+            "--print-json-schema" => format!("{:#}", EnvDocs::of::<T>().to_json_schema()),
+            "--print-helm-env" => EnvDocs::of::<T>().to_helm_env(&helm_secret_name()),
+            "--check-config" => check_config::<T>(),
+            _ => continue,
+        };
+        println!("{docs}");
+        std::process::exit(0);
+    }
+}
+
+/// The name of the Kubernetes secret used by `--print-helm-env`, derived from
+/// the name of the running binary.
+fn helm_secret_name() -> String {
+    let binary = std::env::args_os()
+        .next()
+        .and_then(|arg0| {
+            std::path::Path::new(&arg0)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+        })
+        .unwrap_or_else(|| "app".to_owned());
+    format!("{binary}-secrets")
+}
+
+/// Check the config `T` in the source used by [`try_init`], printing the
+/// report and exiting with a non-zero status code if any problems are found.
+/// Returns the report otherwise.
+fn check_config<T: Init4Config>() -> String {
+    let source = ConfigFile::env_source::<T>().unwrap_or_else(|err| {
+        eprintln!("{err}");
+        std::process::exit(1);
+    });
+    let check = if T::ENV_PREFIXES.is_empty() {
+        ConfigCheck::new().with_inventory_prefixes::<T>()
+    } else {
+        T::ENV_PREFIXES
+            .iter()
+            .fold(ConfigCheck::new(), |check, prefix| {
+                check.with_prefix(*prefix)
+            })
+    };
+    let report = check.check::<T>(&source);
+    if !report.is_ok() {
+        eprintln!("{report}");
+        std::process::exit(1);
+    }
+    report.to_string()
+}
+
+/// Load config from the environment and initialize metrics and tracing,
+/// returning every configuration error if the config cannot be loaded.
+///
+/// This performs the same steps as [`init`], but returns the
+/// [`FromEnvErrors`] produced by [`FromEnv::from_source_accumulate`] rather than
+/// exiting the process. No global state is initialized if loading fails.
+pub fn try_init<T: Init4Config>() -> Result<ConfigAndGuard<T>, FromEnvErrors> {
+    let source = ConfigFile::env_source::<T>()?;
+    let config = T::from_source_accumulate(&source)?;
 
     let guard = utils::tracing::init_tracing_with_config(config.tracing().clone());
     utils::metrics::init_metrics_with_config(*config.metrics());
 
+    // Aliases are resolved before tracing is installed, so the warnings
+    // logged while loading are lost. Repeat them now.
+    for deprecation in T::check_deprecations() {
+        tracing::warn!("{deprecation}");
+    }
+
+    if config.tracing().log_config.into_inner() {
+        let effective = config.effective_config(&source);
+        tracing::info!(config = %effective.to_json(), "effective configuration");
+    }
+
     // This will install the AWS-LC-Rust TLS provider for rustls, if no other
     // provider has been installed yet
     #[cfg(feature = "rustls")]
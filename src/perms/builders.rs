@@ -10,14 +10,10 @@ use crate::{
     perms::SlotAuthzConfig,
     utils::{
         calc::SlotCalculator,
-        from_env::{FromEnv, FromEnvErr, FromEnvVar},
+        clock::{Clock, SharedClock},
+        from_env::{EnvSource, FromEnv, FromEnvErr, FromEnvVar},
     },
 };
-use serde::{Deserialize, Deserializer};
-
-fn now() -> u64 {
-    chrono::Utc::now().timestamp().try_into().unwrap()
-}
 
 /// Possible errors when permissioning a builder.
 #[derive(Debug, thiserror::Error, Clone, PartialEq, Eq)]
@@ -66,22 +62,21 @@ impl Builder {
 }
 
 impl FromEnvVar for Builder {
-    fn from_env_var(env_var: &str) -> Result<Self, FromEnvErr> {
+    fn from_env_var_in(source: &dyn EnvSource, env_var: &str) -> Result<Self, FromEnvErr> {
         Ok(Self {
-            sub: String::from_env_var(env_var)?,
+            sub: String::from_env_var_in(source, env_var)?,
         })
     }
 }
 
 /// Builders struct to keep track of the builders that are allowed to perform actions.
-#[derive(Clone, Debug, serde::Deserialize, FromEnv)]
-#[from_env(crate)]
+#[derive(Clone, Debug, FromEnv)]
+#[from_env(crate, deserialize)]
 pub struct Builders {
     /// The list of builders.
     ///
     /// This is configured in the environment variable `BUILDERS`,
-    /// as a list of comma-separated UUIDs.
-    #[serde(deserialize_with = "deser_builders")]
+    /// as a list of comma-separated UUIDs, or in a config file as a list.
     #[from_env(
         infallible,
         var = "BUILDERS",
@@ -92,24 +87,35 @@ pub struct Builders {
     /// The slot authorization configuration. See [`SlotAuthzConfig`] for more
     /// information and env vars
     config: SlotAuthzConfig,
-}
-
-fn deser_builders<'de, D>(deser: D) -> Result<Vec<Builder>, D::Error>
-where
-    D: Deserializer<'de>,
-{
-    let s = String::deserialize(deser)?;
-    Ok(split_builders(&s))
-}
 
-fn split_builders(s: &str) -> Vec<Builder> {
-    s.split(',').map(Builder::new).collect()
+    /// The clock used to determine the current slot. Defaults to the system
+    /// clock.
+    #[from_env(skip)]
+    clock: SharedClock,
 }
 
 impl Builders {
     /// Create a new Builders struct.
     pub const fn new(builders: Vec<Builder>, config: SlotAuthzConfig) -> Self {
-        Self { builders, config }
+        Self {
+            builders,
+            config,
+            clock: SharedClock::system(),
+        }
+    }
+
+    /// Use the `clock` to determine the current slot, e.g. a
+    /// [`MockClock`] in tests.
+    ///
+    /// [`MockClock`]: crate::utils::clock::MockClock
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = SharedClock::new(clock);
+        self
+    }
+
+    /// Get the clock used to determine the current slot.
+    pub const fn clock(&self) -> &SharedClock {
+        &self.clock
     }
 
     /// Get the calculator instance.
@@ -139,9 +145,15 @@ impl Builders {
     /// Get the index of the builder that is allowed to sign a block for a
     /// particular timestamp.
     pub const fn index(&self, timestamp: u64) -> usize {
+        self.index_ms(timestamp.saturating_mul(1000))
+    }
+
+    /// Get the index of the builder that is allowed to sign a block for a
+    /// particular timestamp in milliseconds.
+    pub const fn index_ms(&self, timestamp_ms: u64) -> usize {
         self.config
             .calc()
-            .slot_containing(timestamp)
+            .slot_containing_ms(timestamp_ms)
             .expect("host chain has started")
             % self.builders.len()
     }
@@ -149,7 +161,7 @@ impl Builders {
     /// Get the index of the builder that is allowed to sign a block at the
     /// current timestamp.
     pub fn index_now(&self) -> usize {
-        self.index(now())
+        self.index_ms(self.clock.now_ms())
     }
 
     /// Get the builder that is allowed to sign a block at the current timestamp.
@@ -161,7 +173,7 @@ impl Builders {
     fn check_query_bounds(&self) -> Result<(), BuilderPermissionError> {
         let current_slot_time = self
             .calc()
-            .current_point_within_slot()
+            .current_point_within_slot_with(&self.clock)
             .expect("host chain has started");
         if current_slot_time < self.config.block_query_start() {
             return Err(BuilderPermissionError::ActionAttemptTooEarly);
@@ -198,21 +210,24 @@ impl Builders {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::utils::{
+        clock::MockClock,
+        from_env::ConfigFile,
+        test_utils::{env_map, from_vars},
+    };
+    use std::time::Duration;
 
     #[test]
     fn load_builders() {
-        unsafe {
-            std::env::set_var("BUILDERS", "0,1,2,3,4,5");
-
-            std::env::set_var("START_TIMESTAMP", "1");
-            std::env::set_var("SLOT_OFFSET", "0");
-            std::env::set_var("SLOT_DURATION", "12");
-
-            std::env::set_var("BLOCK_QUERY_START", "1");
-            std::env::set_var("BLOCK_QUERY_CUTOFF", "11");
-        };
-
-        let builders = Builders::from_env().unwrap();
+        let builders: Builders = from_vars([
+            ("BUILDERS", "0,1,2,3,4,5"),
+            ("START_TIMESTAMP", "1"),
+            ("SLOT_OFFSET", "0"),
+            ("SLOT_DURATION", "12"),
+            ("BLOCK_QUERY_START", "1"),
+            ("BLOCK_QUERY_CUTOFF", "11"),
+        ])
+        .unwrap();
         assert_eq!(builders.builder_at(0).sub, "0");
         assert_eq!(builders.builder_at(1).sub, "1");
         assert_eq!(builders.builder_at(2).sub, "2");
@@ -220,6 +235,9 @@ mod test {
         assert_eq!(builders.builder_at(4).sub, "4");
         assert_eq!(builders.builder_at(5).sub, "5");
 
+        // Timestamps too large to convert to milliseconds saturate.
+        assert_eq!(builders.index(u64::MAX), builders.index_ms(u64::MAX));
+
         assert_eq!(builders.calc().slot_offset(), 0);
         assert_eq!(builders.calc().slot_duration(), 12);
         assert_eq!(builders.calc().start_timestamp(), 1);
@@ -227,4 +245,93 @@ mod test {
         assert_eq!(builders.config.block_query_start(), 1);
         assert_eq!(builders.config.block_query_cutoff(), 11);
     }
+
+    const BUILDERS_TOML: &str = r#"
+        builders = ["0", "1", "2"]
+
+        [config]
+        block_query_start = 1
+        block_query_cutoff = 11
+
+        [config.calc]
+        start_timestamp = 1
+        slot_offset = 0
+        slot_duration = 12
+    "#;
+
+    #[test]
+    fn deserialize_builders() {
+        let builders: Builders = toml::from_str(BUILDERS_TOML).unwrap();
+        assert_eq!(builders.builders.len(), 3);
+        assert_eq!(builders.builder_at(2).sub, "2");
+        assert_eq!(builders.calc().slot_duration(), 12);
+        assert_eq!(builders.config.block_query_cutoff(), 11);
+    }
+
+    #[test]
+    fn invalid_query_bounds() {
+        let err = toml::from_str::<Builders>(&BUILDERS_TOML.replace("= 11", "= 13")).unwrap_err();
+        assert!(err.to_string().contains("BLOCK_QUERY_CUTOFF"));
+
+        let err = toml::from_str::<Builders>(&BUILDERS_TOML.replace("start = 1", "start = 12"))
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("BLOCK_QUERY_START, BLOCK_QUERY_CUTOFF"));
+
+        let calc = SlotCalculator::new(0, 0, 12);
+        assert!(SlotAuthzConfig::try_new(calc, 13, 1).is_err());
+        assert!(SlotAuthzConfig::try_new(calc, 10, 11).is_err());
+        let config = SlotAuthzConfig::new(calc, 13, 14);
+        assert_eq!(config.block_query_cutoff(), 12);
+        assert_eq!(config.block_query_start(), 12);
+        assert_eq!(SlotAuthzConfig::try_new(calc, 12, 12).unwrap(), config);
+    }
+
+    #[test]
+    fn env_overrides_config_file() {
+        let file = ConfigFile::from_toml(BUILDERS_TOML).unwrap();
+        let overrides = env_map([("BLOCK_QUERY_CUTOFF", "10")]);
+        let source = crate::utils::from_env::LayeredSource::new()
+            .with_layer(overrides)
+            .with_layer(file.source_for(&Builders::inventory()));
+
+        let builders = Builders::from_source(&source).unwrap();
+        assert_eq!(builders.builders.len(), 3);
+        assert_eq!(builders.config.block_query_start(), 1);
+        assert_eq!(builders.config.block_query_cutoff(), 10);
+    }
+
+    #[test]
+    fn permissions_across_slots() {
+        let clock = MockClock::from_secs(12);
+        let calc = SlotCalculator::new(12, 0, 12);
+        let config = SlotAuthzConfig::new(calc, 10, 2);
+        let builders = Builders::new(vec![Builder::new("0"), Builder::new("1")], config)
+            .with_clock(clock.clone());
+
+        // Slot 1 is permissioned to builder 1, from second 2 to second 10.
+        assert_eq!(
+            builders.is_builder_permissioned("1"),
+            Err(BuilderPermissionError::ActionAttemptTooEarly)
+        );
+        clock.advance(Duration::from_secs(2));
+        assert_eq!(builders.is_builder_permissioned("1"), Ok(()));
+        assert!(matches!(
+            builders.is_builder_permissioned("0"),
+            Err(BuilderPermissionError::NotPermissioned(..))
+        ));
+        clock.advance(Duration::from_secs(8));
+        assert_eq!(builders.is_builder_permissioned("1"), Ok(()));
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(
+            builders.is_builder_permissioned("1"),
+            Err(BuilderPermissionError::ActionAttemptTooLate)
+        );
+
+        // Rotation at the slot boundary.
+        clock.set(24 + 2);
+        assert_eq!(builders.current_builder().sub(), "0");
+        assert_eq!(builders.is_builder_permissioned("0"), Ok(()));
+    }
 }
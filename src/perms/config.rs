@@ -1,10 +1,13 @@
-use crate::utils::{calc::SlotCalculator, from_env::FromEnv};
+use crate::utils::{
+    calc::SlotCalculator,
+    from_env::{FromEnv, FromEnvErr},
+};
 
 /// Configuration object that describes the slot time settings for a chain.
 ///
 /// This struct is used to configure the slot authorization system
-#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, FromEnv)]
-#[from_env(crate)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromEnv)]
+#[from_env(crate, deserialize, validate_with = Self::validate)]
 pub struct SlotAuthzConfig {
     /// A [`SlotCalculator`] instance that can be used to calculate the slot
     /// number for a given timestamp.
@@ -14,8 +17,8 @@ pub struct SlotAuthzConfig {
     /// a value of 10 means that requests will not be serviced for the last 2 seconds
     /// of any given slot.
     ///
-    /// On loading from env, the number will be clamped between 0 and slot_duration,
-    /// which is generally expected to be 12 seconds.
+    /// This must not exceed the slot duration, which is generally expected to
+    /// be 12 seconds.
     #[from_env(
         var = "BLOCK_QUERY_CUTOFF",
         desc = "The block query cutoff time in seconds."
@@ -25,8 +28,7 @@ pub struct SlotAuthzConfig {
     /// which requests will not be serviced. E.g. a value of 1 means that
     /// requests will not be serviced for the first second of any given slot.
     ///
-    /// On loading from env, the number will be clamped between 0 and slot_duration, as
-    /// which is generally expected to be 12 seconds.
+    /// This must not exceed the block query cutoff.
     #[from_env(
         var = "BLOCK_QUERY_START",
         desc = "The block query start time in seconds."
@@ -36,15 +38,57 @@ pub struct SlotAuthzConfig {
 
 impl SlotAuthzConfig {
     /// Creates a new `SlotAuthzConfig` with the given parameters, clamping the
-    /// values between 0 and `calc.slot_duration()`.
+    /// block query cutoff between 0 and the shortest slot duration of the
+    /// schedule, and the block query start between 0 and the cutoff.
     pub fn new(calc: SlotCalculator, block_query_cutoff: u8, block_query_start: u8) -> Self {
+        let max_cutoff = (calc.min_slot_duration_ms() / 1000).min(u8::MAX as u64) as u8;
+        let block_query_cutoff = block_query_cutoff.clamp(0, max_cutoff);
         Self {
             calc,
-            block_query_cutoff: block_query_cutoff.clamp(0, calc.slot_duration() as u8),
-            block_query_start: block_query_start.clamp(0, calc.slot_duration() as u8),
+            block_query_cutoff,
+            block_query_start: block_query_start.clamp(0, block_query_cutoff),
         }
     }
 
+    /// Creates a new `SlotAuthzConfig` with the given parameters. Returns an
+    /// error if the block query start exceeds the block query cutoff, or the
+    /// cutoff exceeds the shortest slot duration of the schedule.
+    pub fn try_new(
+        calc: SlotCalculator,
+        block_query_cutoff: u8,
+        block_query_start: u8,
+    ) -> Result<Self, FromEnvErr> {
+        if block_query_start > block_query_cutoff {
+            return Err(FromEnvErr::invalid(
+                &["BLOCK_QUERY_START", "BLOCK_QUERY_CUTOFF"],
+                format!(
+                    "block query start ({block_query_start}) exceeds block query cutoff \
+                     ({block_query_cutoff})"
+                ),
+            ));
+        }
+        if block_query_cutoff as u64 * 1000 > calc.min_slot_duration_ms() {
+            return Err(FromEnvErr::invalid(
+                &["BLOCK_QUERY_CUTOFF"],
+                format!(
+                    "block query cutoff ({block_query_cutoff}s) exceeds slot duration ({}ms)",
+                    calc.min_slot_duration_ms()
+                ),
+            ));
+        }
+        Ok(Self {
+            calc,
+            block_query_cutoff,
+            block_query_start,
+        })
+    }
+
+    /// Check that the config is valid, as for [`Self::try_new`]. This is
+    /// called when loading the config from the environment.
+    pub fn validate(&self) -> Result<(), FromEnvErr> {
+        Self::try_new(self.calc, self.block_query_cutoff, self.block_query_start).map(drop)
+    }
+
     /// Get the slot calculator instance.
     pub const fn calc(&self) -> SlotCalculator {
         self.calc
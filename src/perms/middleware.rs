@@ -4,6 +4,8 @@
 //! based on the current slot and builder configuration.
 
 use crate::perms::Builders;
+#[cfg(feature = "reload")]
+use crate::utils::reload::SharedConfig;
 use axum::{
     extract::Request,
     http::{HeaderValue, StatusCode},
@@ -45,6 +47,28 @@ static DESCRIBE: LazyLock<()> = LazyLock::new(|| {
     describe_counter!(SUCCESS, SUCCESS_DESCR);
 });
 
+/// The builders checked by the middleware, which are either fixed, or
+/// reloaded by a [`Reloadable`].
+///
+/// [`Reloadable`]: crate::utils::reload::Reloadable
+#[derive(Clone)]
+enum BuildersRef {
+    Fixed(Arc<Builders>),
+    #[cfg(feature = "reload")]
+    Shared(SharedConfig<Builders>),
+}
+
+impl BuildersRef {
+    /// Get the current builders.
+    fn get(&self) -> Arc<Builders> {
+        match self {
+            Self::Fixed(builders) => builders.clone(),
+            #[cfg(feature = "reload")]
+            Self::Shared(builders) => builders.get(),
+        }
+    }
+}
+
 /// Possible API error responses when a builder permissioning check fails.
 #[derive(Serialize)]
 struct ApiError {
@@ -109,17 +133,34 @@ impl ApiError {
 /// during the current request.
 ///
 /// Contains a pointer to the [`Builders`] struct, which holds the configuration and
-/// builders for the permissioning system.
+/// builders for the permissioning system. With the `reload` feature, the
+/// builders may instead be reloaded by a [`Reloadable`], in which case each
+/// request is checked against the latest builders.
+///
+/// [`Reloadable`]: crate::utils::reload::Reloadable
 #[derive(Clone)]
 pub struct BuilderPermissioningLayer {
     /// The configured builders.
-    builders: Arc<Builders>,
+    builders: BuildersRef,
 }
 
 impl BuilderPermissioningLayer {
     /// Create a new `BuilderPermissioningLayer` with the given builders.
     pub const fn new(builders: Arc<Builders>) -> Self {
-        Self { builders }
+        Self {
+            builders: BuildersRef::Fixed(builders),
+        }
+    }
+
+    /// Create a new `BuilderPermissioningLayer` with builders that are
+    /// updated by a [`Reloadable`].
+    ///
+    /// [`Reloadable`]: crate::utils::reload::Reloadable
+    #[cfg(feature = "reload")]
+    pub const fn reloadable(builders: SharedConfig<Builders>) -> Self {
+        Self {
+            builders: BuildersRef::Shared(builders),
+        }
     }
 }
 
@@ -145,16 +186,37 @@ impl<S> Layer<S> for BuilderPermissioningLayer {
 ///
 /// Contains a pointer to the [`Builders`] struct, which holds the configuration and
 /// builders for the permissioning system. Meant to be nestable and cheaply cloneable.
+///
+/// The current slot is read from the clock of the [`Builders`], so requests
+/// can be tested deterministically with a [`MockClock`]. See
+/// [`Builders::with_clock`].
+///
+/// [`MockClock`]: crate::utils::clock::MockClock
 #[derive(Clone)]
 pub struct BuilderPermissioningService<S> {
     inner: S,
-    builders: Arc<Builders>,
+    builders: BuildersRef,
 }
 
 impl<S> BuilderPermissioningService<S> {
     /// Create a new `BuilderPermissioningService` with the given inner service and builders.
     pub const fn new(inner: S, builders: Arc<Builders>) -> Self {
-        Self { inner, builders }
+        Self {
+            inner,
+            builders: BuildersRef::Fixed(builders),
+        }
+    }
+
+    /// Create a new `BuilderPermissioningService` with the given inner
+    /// service, and builders that are updated by a [`Reloadable`].
+    ///
+    /// [`Reloadable`]: crate::utils::reload::Reloadable
+    #[cfg(feature = "reload")]
+    pub const fn reloadable(inner: S, builders: SharedConfig<Builders>) -> Self {
+        Self {
+            inner,
+            builders: BuildersRef::Shared(builders),
+        }
     }
 }
 
@@ -187,17 +249,17 @@ where
         LazyLock::force(&DESCRIBE);
 
         Box::pin(async move {
-            let current_slot = this.builders.calc().current_slot();
+            let builders = this.builders.get();
+            let current_slot = builders.calc().current_slot_with(builders.clock());
             let span = tracing::info_span!(
                 "builder::permissioning",
                 otel.status_code = tracing::field::Empty,
-                permissioned_builder = this.builders.current_builder().sub(),
+                permissioned_builder = builders.current_builder().sub(),
                 requesting_builder = tracing::field::Empty,
                 current_slot,
-                current_timepoint_within_slot = this
-                    .builders
+                current_timepoint_within_slot = builders
                     .calc()
-                    .current_point_within_slot()
+                    .current_point_within_slot_with(builders.clock())
                     .expect("host chain has started"),
             );
 
@@ -220,7 +282,7 @@ where
 
             span.record("requesting_builder", sub);
 
-            if let Err(err) = this.builders.is_builder_permissioned(sub) {
+            if let Err(err) = builders.is_builder_permissioned(sub) {
                 span.set_status(Status::Error {
                     description: Cow::Owned(err.to_string()),
                 });
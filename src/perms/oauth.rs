@@ -4,7 +4,7 @@
 use crate::deps::tracing::Instrument;
 use crate::{
     deps::tracing::{debug, warn},
-    utils::from_env::FromEnv,
+    utils::from_env::{FromEnv, Seconds, Secret},
 };
 use core::fmt;
 use eyre::eyre;
@@ -36,9 +36,10 @@ pub struct OAuthConfig {
     /// OAuth client secret for the builder.
     #[from_env(
         var = "OAUTH_CLIENT_SECRET",
-        desc = "OAuth client secret for the builder"
+        desc = "OAuth client secret for the builder",
+        file
     )]
-    pub oauth_client_secret: String,
+    pub oauth_client_secret: Secret<String>,
     /// OAuth authenticate URL for the builder for performing OAuth logins.
     #[from_env(
         var = "OAUTH_AUTHENTICATE_URL",
@@ -51,12 +52,23 @@ pub struct OAuthConfig {
         desc = "OAuth token URL for the builder to get an OAuth2 access token"
     )]
     pub oauth_token_url: url::Url,
-    /// The oauth token refresh interval in seconds.
+    /// The oauth token refresh interval, e.g. `60s` or `5m`. Bare numbers are
+    /// seconds.
     #[from_env(
         var = "AUTH_TOKEN_REFRESH_INTERVAL",
-        desc = "The oauth token refresh interval in seconds"
+        desc = "The oauth token refresh interval, e.g. `60s` or `5m`. Bare numbers are seconds",
+        validate = nonzero_interval
     )]
-    pub oauth_token_refresh_interval: u64,
+    pub oauth_token_refresh_interval: Seconds,
+}
+
+/// The refresh interval must be non-zero, as [`tokio::time::interval`] panics
+/// on a zero period.
+const fn nonzero_interval(interval: &Seconds) -> Result<(), &'static str> {
+    if interval.0.is_zero() {
+        return Err("must be greater than zero");
+    }
+    Ok(())
 }
 
 impl OAuthConfig {
@@ -89,7 +101,9 @@ impl Authenticator {
     /// Creates a new Authenticator from the provided builder config.
     pub fn new(config: &OAuthConfig) -> Self {
         let client = BasicClient::new(ClientId::new(config.oauth_client_id.clone()))
-            .set_client_secret(ClientSecret::new(config.oauth_client_secret.clone()))
+            .set_client_secret(ClientSecret::new(
+                config.oauth_client_secret.expose().clone(),
+            ))
             .set_auth_uri(AuthUrl::from_url(config.oauth_authenticate_url.clone()))
             .set_token_uri(TokenUrl::from_url(config.oauth_token_url.clone()));
 
@@ -170,8 +184,7 @@ impl Authenticator {
 
     /// Create a future that contains the periodic refresh loop.
     async fn task_future(self) {
-        let duration = tokio::time::Duration::from_secs(self.config.oauth_token_refresh_interval);
-        let mut interval = tokio::time::interval(duration);
+        let mut interval = tokio::time::interval(*self.config.oauth_token_refresh_interval);
         interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
 
         loop {
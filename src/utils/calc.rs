@@ -1,7 +1,39 @@
-use crate::utils::from_env::{EnvItemInfo, FromEnv, FromEnvErr, FromEnvVar};
+use crate::utils::{
+    clock::{Clock, SystemClock},
+    from_env::{
+        prefixed_var, ConfigFile, EffectiveConfig, EffectiveValue, EnvItemInfo, EnvSource, FromEnv,
+        FromEnvErr, FromEnvVar, Interner, Seconds, ValueSource,
+    },
+};
 #[allow(deprecated)]
 use signet_constants::{mainnet, parmigiana, pecorino, test_utils, KnownChains};
-use std::str::FromStr;
+use std::{collections::BTreeMap, str::FromStr, time::Duration};
+
+/// The number of slots per epoch on Ethereum and its testnets.
+pub const DEFAULT_SLOTS_PER_EPOCH: usize = 32;
+
+/// Slot-time hard forks of Ethereum mainnet. None are scheduled yet.
+pub const MAINNET_SLOT_SCHEDULE: &[SlotSegment] = &[];
+
+/// Slot-time hard forks of the Parmigiana host network. None are scheduled
+/// yet.
+pub const PARMIGIANA_HOST_SLOT_SCHEDULE: &[SlotSegment] = &[];
+
+/// Slot-time hard forks of the Pecorino host network. None are scheduled.
+pub const PECORINO_HOST_SLOT_SCHEDULE: &[SlotSegment] = &[];
+
+/// Slot-time hard forks of the test host network. None are scheduled.
+pub const TEST_HOST_SLOT_SCHEDULE: &[SlotSegment] = &[];
+
+/// Schedules loaded at runtime, leaked to satisfy the `'static` bound of
+/// [`SlotCalculator::with_schedule`]. Each distinct schedule is leaked at most
+/// once.
+static SCHEDULES: Interner<[SlotSegment]> = Interner::new();
+
+#[cfg(feature = "slot_ticker")]
+mod ticker;
+#[cfg(feature = "slot_ticker")]
+pub use ticker::{SlotTick, SlotTicker};
 
 /// A slot calculator, which can calculate slot numbers, windows, and offsets
 /// for a given chain.
@@ -10,6 +42,12 @@ use std::str::FromStr;
 ///
 /// `slots` are indices, and use `usize` for their type.
 /// `timestamps` are in Unix Epoch seconds, and use `u64` for their type.
+/// Methods with an `_ms` suffix take and return timestamps and durations in
+/// milliseconds instead.
+///
+/// Slot durations may be shorter than a second, e.g. for fast local devnets.
+/// The seconds methods round timestamps down to the second, so for such
+/// chains the `_ms` methods should be used.
 ///
 /// It is recommended that literal integers passed to these functions be
 /// explicitly typed, e.g. `0u64`, `12usize`, etc., to avoid confusion in
@@ -52,12 +90,40 @@ use std::str::FromStr;
 /// The `+ 1` is added because the first slot is the slot at `slot_offset`,
 /// which ENDS at `start_timestamp`. I.e. a timestamp at `start_timestamp` is
 /// in slot `slot_offset + 1`.
-#[derive(Debug, Copy, Clone, PartialEq, Eq, serde::Deserialize)]
+///
+/// ## Slot schedules
+///
+/// The slot duration may change at slot-time hard forks. These are described
+/// by a schedule of [`SlotSegment`]s, each of which sets a new slot duration
+/// from its activation timestamp onwards. Slot numbering continues across
+/// segments, i.e. the first slot of a segment directly follows the last slot
+/// of the previous one, and every method accounts for the schedule.
+///
+/// ## Epochs
+///
+/// Slots are grouped into epochs of `slots_per_epoch` slots, which defaults
+/// to [`DEFAULT_SLOTS_PER_EPOCH`]. Epoch `e` contains the slots
+/// `e * slots_per_epoch..(e + 1) * slots_per_epoch`.
+///
+/// ## Deserialization
+///
+/// The calculator deserializes from the same keys and values as a
+/// [`ConfigFile`], e.g.
+///
+/// ```toml
+/// start_timestamp = 1606824023
+/// slot_offset = 0
+/// slot_duration = "12s"
+///
+/// [slot_schedule]
+/// 1800000000 = "6s"
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct SlotCalculator {
-    /// The start timestamp. This is the timestamp of the header to start the
-    /// PoS chain. That header occupies a specific slot (the `slot_offset`). The
-    /// `start_timestamp` is the END of that slot.
-    start_timestamp: u64,
+    /// The start timestamp (in milliseconds). This is the timestamp of the
+    /// header to start the PoS chain. That header occupies a specific slot
+    /// (the `slot_offset`). The `start_timestamp` is the END of that slot.
+    start_timestamp_ms: u64,
 
     /// This is the number of the slot containing the block which contains the
     /// `start_timestamp`.
@@ -67,18 +133,221 @@ pub struct SlotCalculator {
     /// Holesky).
     slot_offset: usize,
 
-    /// The slot duration (in seconds).
-    slot_duration: u64,
+    /// The slot duration (in milliseconds).
+    slot_duration_ms: u64,
+
+    /// The number of slots in each epoch.
+    slots_per_epoch: usize,
+
+    /// The segments after the initial one, in activation order.
+    schedule: &'static [SlotSegment],
+}
+
+/// A segment of a [`SlotCalculator`]'s schedule, in which slots have a
+/// different duration than in the previous segment.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct SlotSegment {
+    /// The timestamp at which the segment activates, in milliseconds. This
+    /// must be a slot boundary of the previous segment.
+    pub activation_timestamp_ms: u64,
+    /// The slot duration from the activation timestamp onwards, in
+    /// milliseconds.
+    pub slot_duration_ms: u64,
+}
+
+impl SlotSegment {
+    /// Create a new segment, with the activation timestamp and slot duration
+    /// in seconds. Values too large to express in milliseconds saturate.
+    pub const fn new(activation_timestamp: u64, slot_duration: u64) -> Self {
+        Self::new_ms(
+            activation_timestamp.saturating_mul(1000),
+            slot_duration.saturating_mul(1000),
+        )
+    }
+
+    /// Create a new segment, with the activation timestamp and slot duration
+    /// in milliseconds.
+    pub const fn new_ms(activation_timestamp_ms: u64, slot_duration_ms: u64) -> Self {
+        Self {
+            activation_timestamp_ms,
+            slot_duration_ms,
+        }
+    }
+}
+
+/// A segment, resolved to the number of its first slot.
+#[derive(Debug, Copy, Clone)]
+struct Segment {
+    /// The start timestamp of the first slot, in milliseconds.
+    start_ms: u64,
+    /// The number of the first slot.
+    first_slot: usize,
+    /// The slot duration, in milliseconds.
+    duration_ms: u64,
+}
+
+impl Segment {
+    /// The segment following this one, which activates at `next`.
+    const fn next(self, next: SlotSegment) -> Self {
+        let slots = (next.activation_timestamp_ms - self.start_ms) / self.duration_ms;
+        Self {
+            start_ms: next.activation_timestamp_ms,
+            first_slot: self.first_slot + slots as usize,
+            duration_ms: next.slot_duration_ms,
+        }
+    }
+
+    /// The start timestamp of the `slot`, in milliseconds.
+    const fn slot_start_ms(&self, slot: usize) -> u64 {
+        if slot >= self.first_slot {
+            self.start_ms + (slot - self.first_slot) as u64 * self.duration_ms
+        } else {
+            self.start_ms - (self.first_slot - slot) as u64 * self.duration_ms
+        }
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for SlotCalculator {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        ConfigFile::deserialize_config(deserializer)
+    }
 }
 
 impl SlotCalculator {
-    /// Create a new slot calculator.
+    /// Create a new slot calculator. Values too large to express in
+    /// milliseconds saturate.
     pub const fn new(start_timestamp: u64, slot_offset: usize, slot_duration: u64) -> Self {
+        Self::new_ms(
+            start_timestamp.saturating_mul(1000),
+            slot_offset,
+            slot_duration.saturating_mul(1000),
+        )
+    }
+
+    /// Create a new slot calculator, with the start timestamp and slot
+    /// duration in milliseconds.
+    pub const fn new_ms(
+        start_timestamp_ms: u64,
+        slot_offset: usize,
+        slot_duration_ms: u64,
+    ) -> Self {
         Self {
-            start_timestamp,
+            start_timestamp_ms,
             slot_offset,
-            slot_duration,
+            slot_duration_ms,
+            slots_per_epoch: DEFAULT_SLOTS_PER_EPOCH,
+            schedule: &[],
+        }
+    }
+
+    /// Set the schedule of slot-time hard forks, in activation order. Each
+    /// segment must activate after the previous one, at one of its slot
+    /// boundaries, and no slot duration may be zero. See [`Self::validate`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the schedule is invalid. In a `const` context, this fails to
+    /// compile instead.
+    pub const fn with_schedule(mut self, schedule: &'static [SlotSegment]) -> Self {
+        assert!(
+            self.schedule_is_valid(schedule),
+            "invalid slot schedule, see `SlotCalculator::validate`"
+        );
+        self.schedule = schedule;
+        self
+    }
+
+    /// Set the schedule of slot-time hard forks from segments built at
+    /// runtime, e.g. loaded from a file. The calculator is validated with the
+    /// new schedule, see [`Self::validate`].
+    pub fn with_schedule_from(mut self, schedule: &[SlotSegment]) -> Result<Self, FromEnvErr> {
+        self.validate_schedule(schedule)?;
+        self.schedule = SCHEDULES.intern(schedule, |v| v.into());
+        Ok(self)
+    }
+
+    /// Check that the slot durations and the number of slots per epoch are
+    /// not zero, and that each segment of the schedule activates after the
+    /// previous one, at one of its slot boundaries.
+    pub fn validate(&self) -> Result<(), FromEnvErr> {
+        self.validate_schedule(self.schedule)
+    }
+
+    /// Validate the calculator as if it had the `schedule`. See
+    /// [`Self::validate`].
+    fn validate_schedule(&self, schedule: &[SlotSegment]) -> Result<(), FromEnvErr> {
+        if self.slot_duration_ms == 0 {
+            return Err(FromEnvErr::invalid(
+                &["SLOT_DURATION"],
+                "slot duration must be at least one millisecond",
+            ));
+        }
+        if self.slots_per_epoch == 0 {
+            return Err(FromEnvErr::invalid(
+                &["SLOTS_PER_EPOCH"],
+                "slots per epoch must not be zero",
+            ));
+        }
+        let mut segment = self.first_segment();
+        for next in schedule {
+            let invalid = |reason: String| FromEnvErr::invalid(&["SLOT_SCHEDULE"], reason);
+            if next.slot_duration_ms == 0 {
+                return Err(invalid(format!(
+                    "slot duration of the segment at {}ms must be at least one millisecond",
+                    next.activation_timestamp_ms
+                )));
+            }
+            if next.activation_timestamp_ms <= segment.start_ms {
+                return Err(invalid(format!(
+                    "segment at {}ms does not activate after {}ms",
+                    next.activation_timestamp_ms, segment.start_ms
+                )));
+            }
+            if !(next.activation_timestamp_ms - segment.start_ms)
+                .is_multiple_of(segment.duration_ms)
+            {
+                return Err(invalid(format!(
+                    "segment at {}ms does not activate at a slot boundary",
+                    next.activation_timestamp_ms
+                )));
+            }
+            segment = segment.next(*next);
         }
+        Ok(())
+    }
+
+    /// True if [`Self::validate_schedule`] accepts the `schedule`, ignoring
+    /// the number of slots per epoch. This guards the arithmetic of
+    /// [`Segment::next`].
+    const fn schedule_is_valid(&self, schedule: &[SlotSegment]) -> bool {
+        let mut segment = self.first_segment();
+        let mut i = 0;
+        while i < schedule.len() {
+            let next = schedule[i];
+            if segment.duration_ms == 0
+                || next.slot_duration_ms == 0
+                || next.activation_timestamp_ms <= segment.start_ms
+                || !(next.activation_timestamp_ms - segment.start_ms)
+                    .is_multiple_of(segment.duration_ms)
+            {
+                return false;
+            }
+            segment = segment.next(next);
+            i += 1;
+        }
+        true
+    }
+
+    /// Set the number of slots per epoch. Defaults to
+    /// [`DEFAULT_SLOTS_PER_EPOCH`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `slots_per_epoch` is zero.
+    pub const fn with_slots_per_epoch(mut self, slots_per_epoch: usize) -> Self {
+        assert!(slots_per_epoch != 0, "slots per epoch must not be zero");
+        self.slots_per_epoch = slots_per_epoch;
+        self
     }
 
     /// Create a new slot calculator for Holesky.
@@ -86,20 +355,17 @@ impl SlotCalculator {
         // begin slot calculation for Holesky from block number 1, slot number 2, timestamp 1695902424
         // because of a strange 324 second gap between block 0 and 1 which
         // should have been 27 slots, but which is recorded as 2 slots in chain data
-        Self {
-            start_timestamp: 1695902424,
-            slot_offset: 2,
-            slot_duration: 12,
-        }
+        Self::new(1695902424, 2, 12)
     }
 
     /// Create a new slot calculator for Parmigiana host network.
     pub const fn parmigiana_host() -> Self {
-        Self {
-            start_timestamp: parmigiana::HOST_START_TIMESTAMP,
-            slot_offset: parmigiana::HOST_SLOT_OFFSET as usize,
-            slot_duration: parmigiana::HOST_SLOT_DURATION,
-        }
+        Self::new(
+            parmigiana::HOST_START_TIMESTAMP,
+            parmigiana::HOST_SLOT_OFFSET as usize,
+            parmigiana::HOST_SLOT_DURATION,
+        )
+        .with_schedule(PARMIGIANA_HOST_SLOT_SCHEDULE)
     }
 
     /// Create a new slot calculator for Pecorino host network.
@@ -109,25 +375,32 @@ impl SlotCalculator {
         reason = "This deprecated function consumes deprecated consts"
     )]
     pub const fn pecorino_host() -> Self {
-        Self {
-            start_timestamp: pecorino::HOST_START_TIMESTAMP,
-            slot_offset: pecorino::HOST_SLOT_OFFSET as usize,
-            slot_duration: pecorino::HOST_SLOT_DURATION,
-        }
+        Self::new(
+            pecorino::HOST_START_TIMESTAMP,
+            pecorino::HOST_SLOT_OFFSET as usize,
+            pecorino::HOST_SLOT_DURATION,
+        )
+        .with_schedule(PECORINO_HOST_SLOT_SCHEDULE)
     }
 
     /// Create a new slot calculator for Ethereum mainnet.
     pub const fn mainnet() -> Self {
-        Self {
-            start_timestamp: mainnet::HOST_START_TIMESTAMP,
-            slot_offset: mainnet::HOST_SLOT_OFFSET as usize,
-            slot_duration: mainnet::HOST_SLOT_DURATION,
-        }
+        Self::new(
+            mainnet::HOST_START_TIMESTAMP,
+            mainnet::HOST_SLOT_OFFSET as usize,
+            mainnet::HOST_SLOT_DURATION,
+        )
+        .with_schedule(MAINNET_SLOT_SCHEDULE)
     }
 
     /// The timestamp of the first PoS block in the chain.
     pub const fn start_timestamp(&self) -> u64 {
-        self.start_timestamp
+        self.start_timestamp_ms / 1000
+    }
+
+    /// The timestamp of the first PoS block in the chain, in milliseconds.
+    pub const fn start_timestamp_ms(&self) -> u64 {
+        self.start_timestamp_ms
     }
 
     /// The slot number of the first PoS block in the chain.
@@ -135,54 +408,174 @@ impl SlotCalculator {
         self.slot_offset
     }
 
-    /// The slot duration, usually 12 seconds.
+    /// The slot duration at the chain's start, usually 12 seconds. This is
+    /// rounded down to the second, see [`Self::slot_duration_ms`].
     pub const fn slot_duration(&self) -> u64 {
-        self.slot_duration
+        self.slot_duration_ms / 1000
+    }
+
+    /// The slot duration at the chain's start, in milliseconds. See
+    /// [`Self::slot_duration_ms_of`] for chains with a schedule.
+    pub const fn slot_duration_ms(&self) -> u64 {
+        self.slot_duration_ms
+    }
+
+    /// The duration of a given slot, in milliseconds.
+    pub const fn slot_duration_ms_of(&self, slot: usize) -> u64 {
+        self.segment_of_slot(slot).duration_ms
+    }
+
+    /// The shortest slot duration in the schedule, in milliseconds.
+    pub const fn min_slot_duration_ms(&self) -> u64 {
+        let mut min = self.slot_duration_ms;
+        let mut i = 0;
+        while i < self.schedule.len() {
+            if self.schedule[i].slot_duration_ms < min {
+                min = self.schedule[i].slot_duration_ms;
+            }
+            i += 1;
+        }
+        min
     }
 
-    /// The offset in seconds between UTC time and slot mining times
-    const fn slot_utc_offset(&self) -> u64 {
-        self.start_timestamp % self.slot_duration
+    /// The schedule of slot-time hard forks, after the chain's start.
+    pub const fn schedule(&self) -> &'static [SlotSegment] {
+        self.schedule
+    }
+
+    /// The initial segment, starting at the chain's start.
+    const fn first_segment(&self) -> Segment {
+        Segment {
+            start_ms: self.start_timestamp_ms,
+            first_slot: self.slot_offset + 1,
+            duration_ms: self.slot_duration_ms,
+        }
+    }
+
+    /// The segment containing the timestamp, in milliseconds. Timestamps
+    /// before the chain's start are in the initial segment.
+    const fn segment_at_ms(&self, timestamp_ms: u64) -> Segment {
+        let mut segment = self.first_segment();
+        let mut i = 0;
+        while i < self.schedule.len() && self.schedule[i].activation_timestamp_ms <= timestamp_ms {
+            segment = segment.next(self.schedule[i]);
+            i += 1;
+        }
+        segment
+    }
+
+    /// The segment containing the slot. Slots before the chain's start are
+    /// in the initial segment.
+    const fn segment_of_slot(&self, slot: usize) -> Segment {
+        let mut segment = self.first_segment();
+        let mut i = 0;
+        while i < self.schedule.len() {
+            let next = segment.next(self.schedule[i]);
+            if slot < next.first_slot {
+                break;
+            }
+            segment = next;
+            i += 1;
+        }
+        segment
+    }
+
+    /// The number of slots in each epoch, usually 32.
+    pub const fn slots_per_epoch(&self) -> usize {
+        self.slots_per_epoch
     }
 
     /// Calculates the slot that contains a given timestamp.
     ///
-    /// Returns `None` if the timestamp is before the chain's start timestamp.
+    /// Returns `None` if the timestamp is before the chain's start timestamp,
+    /// or too large to express in milliseconds.
     pub const fn slot_containing(&self, timestamp: u64) -> Option<usize> {
-        let Some(elapsed) = timestamp.checked_sub(self.start_timestamp) else {
+        let Some(timestamp_ms) = timestamp.checked_mul(1000) else {
             return None;
         };
-        let slots = (elapsed / self.slot_duration) + 1;
-        Some(slots as usize + self.slot_offset)
+        self.slot_containing_ms(timestamp_ms)
+    }
+
+    /// Calculates the slot that contains a given timestamp in milliseconds.
+    ///
+    /// Returns `None` if the timestamp is before the chain's start timestamp.
+    pub const fn slot_containing_ms(&self, timestamp_ms: u64) -> Option<usize> {
+        if timestamp_ms < self.start_timestamp_ms {
+            return None;
+        }
+        let segment = self.segment_at_ms(timestamp_ms);
+        let slots = (timestamp_ms - segment.start_ms) / segment.duration_ms;
+        Some(segment.first_slot + slots as usize)
     }
 
     /// Calculates how many seconds a given timestamp is into its containing
     /// slot.
     ///
-    /// Returns `None` if the timestamp is before the chain's start.
+    /// Returns `None` if the timestamp is before the chain's start, or too
+    /// large to express in milliseconds.
     pub const fn point_within_slot(&self, timestamp: u64) -> Option<u64> {
-        let Some(offset) = timestamp.checked_sub(self.slot_utc_offset()) else {
+        let Some(timestamp_ms) = timestamp.checked_mul(1000) else {
             return None;
         };
-        Some(offset % self.slot_duration)
+        match self.point_within_slot_ms(timestamp_ms) {
+            Some(point) => Some(point / 1000),
+            None => None,
+        }
+    }
+
+    /// Calculates how many milliseconds a given timestamp in milliseconds is
+    /// into its containing slot.
+    ///
+    /// Returns `None` if the timestamp is before the chain's start.
+    pub const fn point_within_slot_ms(&self, timestamp_ms: u64) -> Option<u64> {
+        let segment = self.segment_at_ms(timestamp_ms);
+        // Before the chain's start, this offsets by the UTC offset of the
+        // slot boundaries.
+        let Some(offset) = timestamp_ms.checked_sub(segment.start_ms % segment.duration_ms) else {
+            return None;
+        };
+        Some(offset % segment.duration_ms)
     }
 
     /// Calculates how many seconds a given timestamp is into a given slot.
     /// Returns `None` if the timestamp is not within the slot.
     pub const fn checked_point_within_slot(&self, slot: usize, timestamp: u64) -> Option<u64> {
-        let calculated = self.slot_containing(timestamp);
+        let Some(timestamp_ms) = timestamp.checked_mul(1000) else {
+            return None;
+        };
+        match self.checked_point_within_slot_ms(slot, timestamp_ms) {
+            Some(point) => Some(point / 1000),
+            None => None,
+        }
+    }
+
+    /// Calculates how many milliseconds a given timestamp in milliseconds is
+    /// into a given slot. Returns `None` if the timestamp is not within the
+    /// slot.
+    pub const fn checked_point_within_slot_ms(
+        &self,
+        slot: usize,
+        timestamp_ms: u64,
+    ) -> Option<u64> {
+        let calculated = self.slot_containing_ms(timestamp_ms);
         if calculated.is_none() || calculated.unwrap() != slot {
             return None;
         }
-        self.point_within_slot(timestamp)
+        self.point_within_slot_ms(timestamp_ms)
     }
 
     /// Calculates the start and end timestamps for a given slot
     pub const fn slot_window(&self, slot_number: usize) -> std::ops::Range<u64> {
-        let end_of_slot =
-            ((slot_number - self.slot_offset) as u64 * self.slot_duration) + self.start_timestamp;
-        let start_of_slot = end_of_slot - self.slot_duration;
-        start_of_slot..end_of_slot
+        let window = self.slot_window_ms(slot_number);
+        window.start / 1000..window.end / 1000
+    }
+
+    /// Calculates the start and end timestamps in milliseconds for a given
+    /// slot.
+    pub const fn slot_window_ms(&self, slot_number: usize) -> std::ops::Range<u64> {
+        let segment = self.segment_of_slot(slot_number);
+        let start_of_slot = segment.slot_start_ms(slot_number);
+        start_of_slot..start_of_slot + segment.duration_ms
     }
 
     /// Calculates the start timestamp of a given slot.
@@ -190,11 +583,21 @@ impl SlotCalculator {
         self.slot_window(slot_number).start
     }
 
+    /// Calculates the start timestamp in milliseconds of a given slot.
+    pub const fn slot_start_ms(&self, slot_number: usize) -> u64 {
+        self.slot_window_ms(slot_number).start
+    }
+
     /// Calculates the end timestamp of a given slot.
     pub const fn slot_end(&self, slot_number: usize) -> u64 {
         self.slot_window(slot_number).end
     }
 
+    /// Calculates the end timestamp in milliseconds of a given slot.
+    pub const fn slot_end_ms(&self, slot_number: usize) -> u64 {
+        self.slot_window_ms(slot_number).end
+    }
+
     /// Calculate the timestamp that will appear in the header of the block at
     /// the given slot number (if any block is produced). This is an alias for
     /// [`Self::slot_end`].
@@ -216,6 +619,19 @@ impl SlotCalculator {
         Some(self.slot_window(slot))
     }
 
+    /// Calculates the slot window in milliseconds for the slot that contains
+    /// the given timestamp in milliseconds. See
+    /// [`Self::slot_window_for_timestamp`].
+    pub const fn slot_window_for_timestamp_ms(
+        &self,
+        timestamp_ms: u64,
+    ) -> Option<std::ops::Range<u64>> {
+        let Some(slot) = self.slot_containing_ms(timestamp_ms) else {
+            return None;
+        };
+        Some(self.slot_window_ms(slot))
+    }
+
     /// Calcuates the start timestamp for the slot that contains the given
     /// timestamp.
     pub const fn slot_start_for_timestamp(&self, timestamp: u64) -> Option<u64> {
@@ -226,6 +642,16 @@ impl SlotCalculator {
         }
     }
 
+    /// Calculates the start timestamp in milliseconds for the slot that
+    /// contains the given timestamp in milliseconds.
+    pub const fn slot_start_for_timestamp_ms(&self, timestamp_ms: u64) -> Option<u64> {
+        if let Some(window) = self.slot_window_for_timestamp_ms(timestamp_ms) {
+            Some(window.start)
+        } else {
+            None
+        }
+    }
+
     /// Calculates the end timestamp for the slot that contains to the given
     /// timestamp.
     pub const fn slot_end_for_timestamp(&self, timestamp: u64) -> Option<u64> {
@@ -236,102 +662,316 @@ impl SlotCalculator {
         }
     }
 
+    /// Calculates the end timestamp in milliseconds for the slot that
+    /// contains the given timestamp in milliseconds.
+    pub const fn slot_end_for_timestamp_ms(&self, timestamp_ms: u64) -> Option<u64> {
+        if let Some(window) = self.slot_window_for_timestamp_ms(timestamp_ms) {
+            Some(window.end)
+        } else {
+            None
+        }
+    }
+
     /// The current slot number.
     ///
     /// Returns `None` if the current time is before the chain's start
     /// timestamp.
     pub fn current_slot(&self) -> Option<usize> {
-        self.slot_containing(chrono::Utc::now().timestamp() as u64)
+        self.current_slot_with(&SystemClock)
+    }
+
+    /// The slot number at the current time of the `clock`.
+    ///
+    /// Returns `None` if the time is before the chain's start timestamp.
+    pub fn current_slot_with(&self, clock: &dyn Clock) -> Option<usize> {
+        self.slot_containing_ms(clock.now_ms())
     }
 
     /// The current number of seconds into the slot.
     pub fn current_point_within_slot(&self) -> Option<u64> {
-        self.point_within_slot(chrono::Utc::now().timestamp() as u64)
+        self.current_point_within_slot_with(&SystemClock)
+    }
+
+    /// The number of seconds into the slot at the current time of the
+    /// `clock`.
+    pub fn current_point_within_slot_with(&self, clock: &dyn Clock) -> Option<u64> {
+        self.point_within_slot(clock.now())
     }
 
     /// The current number of milliseconds into the slot.
-    ///
-    /// Truncates the millisecond timestamp to seconds, then uses that to
-    /// calculate the point within the slot, adding back the milliseconds
-    /// remainder to the final result to provide millisecond precision.
     pub fn current_point_within_slot_ms(&self) -> Option<u64> {
-        // NB: Only fetch from now() object once and reuse
-        let timestamp_ms = chrono::Utc::now().timestamp_millis() as u64;
-        let timestamp_s = timestamp_ms / 1000;
-        let fractional = timestamp_ms % 1000;
+        self.current_point_within_slot_ms_with(&SystemClock)
+    }
 
-        self.point_within_slot(timestamp_s)
-            .map(|point| point * 1000 + fractional)
+    /// The number of milliseconds into the slot at the current time of the
+    /// `clock`.
+    pub fn current_point_within_slot_ms_with(&self, clock: &dyn Clock) -> Option<u64> {
+        self.point_within_slot_ms(clock.now_ms())
     }
 
     /// Calculates the slot that starts at the given timestamp.
     /// Returns `None` if the timestamp is not a slot boundary.
     /// Returns `None` if the timestamp is before the chain's start timestamp.
     pub fn slot_starting_at(&self, timestamp: u64) -> Option<usize> {
-        let elapsed = timestamp.checked_sub(self.start_timestamp)?;
+        self.slot_starting_at_ms(timestamp.checked_mul(1000)?)
+    }
+
+    /// Calculates the slot that starts at the given timestamp in
+    /// milliseconds. See [`Self::slot_starting_at`].
+    pub fn slot_starting_at_ms(&self, timestamp_ms: u64) -> Option<usize> {
+        let segment = self.segment_at_ms(timestamp_ms);
+        let elapsed = timestamp_ms.checked_sub(segment.start_ms)?;
 
-        if elapsed % self.slot_duration != 0 {
+        if elapsed % segment.duration_ms != 0 {
             return None;
         }
 
-        self.slot_containing(timestamp)
+        self.slot_containing_ms(timestamp_ms)
     }
 
     /// Calculates the slot that ends at the given timestamp.
     /// Returns `None` if the timestamp is not a slot boundary.
     /// Returns `None` if the timestamp is before the chain's start timestamp.
     pub fn slot_ending_at(&self, timestamp: u64) -> Option<usize> {
-        let elapsed = timestamp.checked_sub(self.start_timestamp)?;
+        self.slot_ending_at_ms(timestamp.checked_mul(1000)?)
+    }
+
+    /// Calculates the slot that ends at the given timestamp in milliseconds.
+    /// See [`Self::slot_ending_at`].
+    pub fn slot_ending_at_ms(&self, timestamp_ms: u64) -> Option<usize> {
+        self.slot_starting_at_ms(timestamp_ms)
+            .and_then(|slot| slot.checked_sub(1))
+    }
+
+    /// Calculates the epoch that contains a given slot.
+    ///
+    /// Slots are numbered from 1, i.e. the slot number is the beacon slot
+    /// number plus one, so epochs are computed from the beacon slot number to
+    /// align with beacon chain epochs.
+    pub const fn epoch_of_slot(&self, slot: usize) -> usize {
+        slot.saturating_sub(1) / self.slots_per_epoch
+    }
+
+    /// Calculates the first slot of a given epoch. See
+    /// [`Self::epoch_of_slot`].
+    pub const fn epoch_start_slot(&self, epoch: usize) -> usize {
+        epoch * self.slots_per_epoch + 1
+    }
 
-        if elapsed % self.slot_duration != 0 {
+    /// Calculates the epoch that contains a given timestamp.
+    ///
+    /// Returns `None` if the timestamp is before the chain's start timestamp,
+    /// or too large to express in milliseconds.
+    pub const fn epoch_containing(&self, timestamp: u64) -> Option<usize> {
+        let Some(timestamp_ms) = timestamp.checked_mul(1000) else {
             return None;
+        };
+        self.epoch_containing_ms(timestamp_ms)
+    }
+
+    /// Calculates the epoch that contains a given timestamp in milliseconds.
+    ///
+    /// Returns `None` if the timestamp is before the chain's start timestamp.
+    pub const fn epoch_containing_ms(&self, timestamp_ms: u64) -> Option<usize> {
+        match self.slot_containing_ms(timestamp_ms) {
+            Some(slot) => Some(self.epoch_of_slot(slot)),
+            None => None,
         }
+    }
 
-        self.slot_containing(timestamp)
-            .and_then(|slot| slot.checked_sub(1))
+    /// Calculates the start and end timestamps for a given epoch, i.e. from
+    /// the start of its first slot to the end of its last slot.
+    ///
+    /// The epoch must not start before the `slot_offset`.
+    pub const fn epoch_window(&self, epoch: usize) -> std::ops::Range<u64> {
+        let window = self.epoch_window_ms(epoch);
+        window.start / 1000..window.end / 1000
+    }
+
+    /// Calculates the start and end timestamps in milliseconds for a given
+    /// epoch. See [`Self::epoch_window`].
+    pub const fn epoch_window_ms(&self, epoch: usize) -> std::ops::Range<u64> {
+        let first_slot = self.epoch_start_slot(epoch);
+        let last_slot = first_slot + self.slots_per_epoch - 1;
+        self.slot_start_ms(first_slot)..self.slot_end_ms(last_slot)
+    }
+
+    /// The current epoch.
+    ///
+    /// Returns `None` if the current time is before the chain's start
+    /// timestamp.
+    pub fn current_epoch(&self) -> Option<usize> {
+        self.current_epoch_with(&SystemClock)
+    }
+
+    /// The epoch at the current time of the `clock`.
+    ///
+    /// Returns `None` if the time is before the chain's start timestamp.
+    pub fn current_epoch_with(&self, clock: &dyn Clock) -> Option<usize> {
+        self.epoch_containing_ms(clock.now_ms())
     }
 }
 
 impl FromEnv for SlotCalculator {
     fn inventory() -> Vec<&'static EnvItemInfo> {
         vec![
-            &EnvItemInfo {
-                var: "CHAIN_NAME",
-                description: "The name of the chain. If set, the other environment variables are ignored.",
-                optional: true,
+            &const {
+                EnvItemInfo::new(
+                    "CHAIN_NAME",
+                    "The name of the chain. If set, the other environment variables are ignored.",
+                )
+                .optional()
+                .type_name("KnownChains")
+                .group("SlotCalculator")
+                .key("chain_name")
+            },
+            &const {
+                EnvItemInfo::new("START_TIMESTAMP", "The start timestamp of the chain in seconds. Required if CHAIN_NAME is not set.")
+                    .optional()
+                    .type_name("u64")
+                    .group("SlotCalculator")
+                    .key("start_timestamp")
+            },
+            &const {
+                EnvItemInfo::new("SLOT_OFFSET", "The number of the slot containing the start timestamp. Required if CHAIN_NAME is not set.")
+                    .optional()
+                    .type_name("usize")
+                    .group("SlotCalculator")
+                    .key("slot_offset")
             },
-            &EnvItemInfo {
-                var: "START_TIMESTAMP",
-                description: "The start timestamp of the chain in seconds. Required if CHAIN_NAME is not set.",
-                optional: true,
+            &const {
+                EnvItemInfo::new("SLOT_DURATION", "The slot duration of the chain in seconds, or with a unit, e.g. `500ms`. Required if CHAIN_NAME is not set.")
+                    .optional()
+                    .type_name("Seconds")
+                    .group("SlotCalculator")
+                    .key("slot_duration")
             },
-            &EnvItemInfo {
-                var: "SLOT_OFFSET",
-                description: "The number of the slot containing the start timestamp. Required if CHAIN_NAME is not set.",
-                optional: true,
+            &const {
+                EnvItemInfo::new(
+                    "SLOTS_PER_EPOCH",
+                    "The number of slots in each epoch. Ignored if CHAIN_NAME is set.",
+                )
+                .optional()
+                .default("32")
+                .type_name("usize")
+                .group("SlotCalculator")
+                .key("slots_per_epoch")
             },
-            &EnvItemInfo {
-                var: "SLOT_DURATION",
-                description: "The slot duration of the chain in seconds. Required if CHAIN_NAME is not set.",
-                optional: true,
+            &const {
+                EnvItemInfo::new("SLOT_SCHEDULE", "Slot-time hard forks, as `activation_timestamp=slot_duration` entries, e.g. `1800000000=6s`. Activation timestamps are in seconds and must be slot boundaries. Ignored if CHAIN_NAME is set.")
+                    .optional()
+                    .type_name("BTreeMap<u64, Seconds>")
+                    .group("SlotCalculator")
+                    .key("slot_schedule")
             },
         ]
     }
 
-    fn from_env() -> Result<Self, FromEnvErr> {
-        if let Ok(slot_calculator) = SlotCalculator::from_env_var("CHAIN_NAME") {
+    fn from_source_with_prefix(source: &dyn EnvSource, prefix: &str) -> Result<Self, FromEnvErr> {
+        let var = |name| prefixed_var(prefix, name);
+
+        if let Ok(slot_calculator) = SlotCalculator::from_env_var_in(source, &var("CHAIN_NAME")) {
             return Ok(slot_calculator);
         }
 
-        let start_timestamp = FromEnvVar::from_env_var("START_TIMESTAMP")?;
-        let slot_offset = FromEnvVar::from_env_var("SLOT_OFFSET")?;
-        let slot_duration = FromEnvVar::from_env_var("SLOT_DURATION")?;
+        let too_large = |name, what: &str| {
+            FromEnvErr::invalid(
+                &[var(name)],
+                format!("{what} is too large to express in milliseconds"),
+            )
+        };
 
-        Ok(Self {
-            start_timestamp,
-            slot_offset,
-            slot_duration,
-        })
+        let start_timestamp = u64::from_env_var_in(source, &var("START_TIMESTAMP"))?;
+        let start_timestamp_ms = start_timestamp
+            .checked_mul(1000)
+            .ok_or_else(|| too_large("START_TIMESTAMP", "start timestamp"))?;
+        let slot_offset = FromEnvVar::from_env_var_in(source, &var("SLOT_OFFSET"))?;
+        let slot_duration = Seconds::from_env_var_in(source, &var("SLOT_DURATION"))?;
+        let slot_duration_ms = u64::try_from(slot_duration.as_millis())
+            .map_err(|_| too_large("SLOT_DURATION", "slot duration"))?;
+        if slot_duration_ms == 0 {
+            return Err(FromEnvErr::invalid(
+                &[var("SLOT_DURATION")],
+                "slot duration must be at least one millisecond",
+            ));
+        }
+
+        let slots_per_epoch = Option::<usize>::from_env_var_in(source, &var("SLOTS_PER_EPOCH"))?
+            .unwrap_or(DEFAULT_SLOTS_PER_EPOCH);
+        if slots_per_epoch == 0 {
+            return Err(FromEnvErr::invalid(
+                &[var("SLOTS_PER_EPOCH")],
+                "slots per epoch must not be zero",
+            ));
+        }
+
+        let schedule =
+            Option::<BTreeMap<u64, Seconds>>::from_env_var_in(source, &var("SLOT_SCHEDULE"))?
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(activation, duration)| {
+                    let activation_ms = activation.checked_mul(1000).ok_or_else(|| {
+                        too_large(
+                            "SLOT_SCHEDULE",
+                            &format!("activation timestamp {activation}"),
+                        )
+                    })?;
+                    let duration_ms = u64::try_from(duration.as_millis()).map_err(|_| {
+                        too_large("SLOT_SCHEDULE", &format!("slot duration at {activation}"))
+                    })?;
+                    Ok(SlotSegment::new_ms(activation_ms, duration_ms))
+                })
+                .collect::<Result<Vec<_>, FromEnvErr>>()?;
+
+        Self::new_ms(start_timestamp_ms, slot_offset, slot_duration_ms)
+            .with_slots_per_epoch(slots_per_epoch)
+            .with_schedule_from(&schedule)
+            .map_err(|err| err.with_prefix(prefix))
+    }
+
+    /// If `CHAIN_NAME` selects a known chain, the other variables are
+    /// reported as coming from that preset, as they are ignored.
+    fn effective_config_with_prefix(
+        &self,
+        source: &dyn EnvSource,
+        prefix: &str,
+    ) -> EffectiveConfig {
+        let items = Self::inventory_with_prefix(prefix);
+        let chain_name = items[0];
+        let chain = source
+            .get(chain_name.var)
+            .ok()
+            .and_then(|name| name.parse::<KnownChains>().ok());
+        let preset = chain.is_some();
+
+        let mut config = EffectiveConfig::new("SlotCalculator");
+        config.push_value(EffectiveValue::resolve(chain_name, source).with_parsed(&chain));
+
+        let schedule: BTreeMap<_, _> = self
+            .schedule
+            .iter()
+            .map(|segment| {
+                (
+                    segment.activation_timestamp_ms / 1000,
+                    Duration::from_millis(segment.slot_duration_ms),
+                )
+            })
+            .collect();
+        let parsed: [&dyn core::fmt::Debug; 5] = [
+            &self.start_timestamp(),
+            &self.slot_offset,
+            &Duration::from_millis(self.slot_duration_ms),
+            &self.slots_per_epoch,
+            &schedule,
+        ];
+        for (item, parsed) in items[1..].iter().zip(parsed) {
+            let mut value = EffectiveValue::resolve(item, source).with_parsed(parsed);
+            if preset {
+                value = value.with_source(ValueSource::Preset(chain_name.var));
+            }
+            config.push_value(value);
+        }
+        config
     }
 }
 
@@ -346,7 +986,8 @@ impl From<KnownChains> for SlotCalculator {
                 test_utils::HOST_START_TIMESTAMP,
                 test_utils::HOST_SLOT_OFFSET as usize,
                 test_utils::HOST_SLOT_DURATION,
-            ),
+            )
+            .with_schedule(TEST_HOST_SLOT_SCHEDULE),
         }
     }
 }
@@ -362,6 +1003,7 @@ impl FromStr for SlotCalculator {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::clock::MockClock;
 
     impl SlotCalculator {
         #[track_caller]
@@ -402,7 +1044,7 @@ mod tests {
         let calculator = SlotCalculator::holesky();
 
         // Just before the start timestamp
-        let just_before = calculator.start_timestamp - 1;
+        let just_before = calculator.start_timestamp() - 1;
         assert_eq!(calculator.slot_containing(just_before), None);
 
         // Timestamp 17
@@ -445,7 +1087,7 @@ mod tests {
         let calculator = SlotCalculator::mainnet();
 
         // Just before the start timestamp
-        let just_before = calculator.start_timestamp - 1;
+        let just_before = calculator.start_timestamp() - 1;
         assert_eq!(calculator.slot_containing(just_before), None);
 
         // Timestamp 17
@@ -518,4 +1160,307 @@ mod tests {
         assert_eq!(calculator.slot_containing(25), Some(3));
         assert_eq!(calculator.slot_containing(35), Some(3));
     }
+
+    #[test]
+    fn current_slot_with_clock() {
+        let calculator = SlotCalculator::new(12, 0, 12);
+        let clock = MockClock::from_secs(11);
+        assert_eq!(calculator.current_slot_with(&clock), None);
+
+        clock.advance(Duration::from_millis(1500));
+        assert_eq!(calculator.current_slot_with(&clock), Some(1));
+        assert_eq!(calculator.current_point_within_slot_with(&clock), Some(0));
+        assert_eq!(
+            calculator.current_point_within_slot_ms_with(&clock),
+            Some(500)
+        );
+
+        clock.set(35);
+        assert_eq!(calculator.current_slot_with(&clock), Some(2));
+        assert_eq!(calculator.current_point_within_slot_with(&clock), Some(11));
+    }
+
+    #[test]
+    fn sub_second_slots() {
+        let calculator = SlotCalculator::new_ms(12_000, 0, 500);
+        assert_eq!(calculator.slot_duration(), 0);
+        assert_eq!(calculator.slot_containing_ms(11_999), None);
+        assert_eq!(calculator.slot_containing_ms(12_000), Some(1));
+        assert_eq!(calculator.slot_containing_ms(12_499), Some(1));
+        assert_eq!(calculator.slot_containing_ms(12_500), Some(2));
+        assert_eq!(calculator.slot_window_ms(2), 12_500..13_000);
+        assert_eq!(calculator.point_within_slot_ms(12_730), Some(230));
+        assert_eq!(
+            calculator.checked_point_within_slot_ms(2, 12_730),
+            Some(230)
+        );
+        assert_eq!(calculator.checked_point_within_slot_ms(1, 12_730), None);
+        assert_eq!(calculator.slot_starting_at_ms(13_000), Some(3));
+        assert_eq!(calculator.slot_ending_at_ms(13_000), Some(2));
+        assert_eq!(calculator.slot_ending_at_ms(13_001), None);
+        // Seconds timestamps are the start of the second.
+        assert_eq!(calculator.slot_containing(13), Some(3));
+    }
+
+    #[test]
+    fn ms_matches_seconds() {
+        let calculator = SlotCalculator::mainnet();
+        let timestamp = 1663224185;
+        let slot = calculator.slot_containing(timestamp).unwrap();
+        assert_eq!(
+            calculator.slot_containing_ms(timestamp * 1000 + 999),
+            Some(slot)
+        );
+        assert_eq!(
+            calculator.slot_window_ms(slot),
+            calculator.slot_start(slot) * 1000..calculator.slot_end(slot) * 1000
+        );
+        assert_eq!(
+            calculator.point_within_slot_ms(timestamp * 1000 + 250),
+            Some(calculator.point_within_slot(timestamp).unwrap() * 1000 + 250)
+        );
+    }
+
+    #[test]
+    fn load_sub_second_duration() {
+        let source = std::collections::HashMap::from([
+            ("START_TIMESTAMP".to_owned(), "12".to_owned()),
+            ("SLOT_OFFSET".to_owned(), "0".to_owned()),
+            ("SLOT_DURATION".to_owned(), "250ms".to_owned()),
+        ]);
+        let calculator = SlotCalculator::from_source(&source).unwrap();
+        assert_eq!(calculator, SlotCalculator::new_ms(12_000, 0, 250));
+
+        let calculator: SlotCalculator = serde_json::from_str(
+            r#"{ "start_timestamp": 12, "slot_offset": 0, "slot_duration": "250ms" }"#,
+        )
+        .unwrap();
+        assert_eq!(calculator.slot_duration_ms(), 250);
+    }
+
+    #[test]
+    fn large_timestamps() {
+        let calculator = SlotCalculator::mainnet();
+        let timestamp = u64::MAX / 500;
+        assert_eq!(calculator.slot_containing(timestamp), None);
+        assert_eq!(calculator.point_within_slot(timestamp), None);
+        assert_eq!(calculator.checked_point_within_slot(1, timestamp), None);
+        assert_eq!(calculator.slot_starting_at(timestamp), None);
+        assert_eq!(calculator.slot_ending_at(timestamp), None);
+        assert_eq!(calculator.epoch_containing(timestamp), None);
+
+        let calculator = SlotCalculator::new(u64::MAX, 0, u64::MAX);
+        assert_eq!(calculator.start_timestamp_ms(), u64::MAX);
+        assert_eq!(calculator.slot_duration_ms(), u64::MAX);
+        assert_eq!(
+            SlotSegment::new(u64::MAX, 1).activation_timestamp_ms,
+            u64::MAX
+        );
+
+        let mut source = std::collections::HashMap::from([
+            ("START_TIMESTAMP".to_owned(), timestamp.to_string()),
+            ("SLOT_OFFSET".to_owned(), "0".to_owned()),
+            ("SLOT_DURATION".to_owned(), "12".to_owned()),
+        ]);
+        let err = SlotCalculator::from_source(&source).unwrap_err();
+        assert!(matches!(err, FromEnvErr::Invalid(..)), "{err}");
+        assert!(err.to_string().contains("START_TIMESTAMP"));
+
+        source.insert("START_TIMESTAMP".to_owned(), "12".to_owned());
+        source.insert("SLOT_SCHEDULE".to_owned(), format!("{timestamp}=6s"));
+        let err = SlotCalculator::from_source(&source).unwrap_err();
+        assert!(err.to_string().contains("SLOT_SCHEDULE"), "{err}");
+    }
+
+    #[test]
+    fn epochs() {
+        let calculator = SlotCalculator::new(12, 0, 12).with_slots_per_epoch(4);
+        // Slot 1 is the first slot of epoch 0.
+        assert_eq!(calculator.epoch_of_slot(1), 0);
+        assert_eq!(calculator.epoch_of_slot(4), 0);
+        assert_eq!(calculator.epoch_of_slot(5), 1);
+        assert_eq!(calculator.epoch_start_slot(0), 1);
+        assert_eq!(calculator.epoch_start_slot(2), 9);
+
+        // Slot 5 starts at 12 + 4 * 12.
+        assert_eq!(calculator.epoch_window(0), 12..60);
+        assert_eq!(calculator.epoch_window(1), 60..108);
+        assert_eq!(calculator.epoch_containing(12), Some(0));
+        assert_eq!(calculator.epoch_containing(59), Some(0));
+        assert_eq!(calculator.epoch_containing(60), Some(1));
+        assert_eq!(calculator.epoch_containing(107), Some(1));
+        assert_eq!(calculator.epoch_containing(108), Some(2));
+        assert_eq!(calculator.epoch_containing(11), None);
+        assert_eq!(
+            calculator.current_epoch_with(&MockClock::from_secs(100)),
+            Some(1)
+        );
+
+        let calculator = SlotCalculator::mainnet();
+        assert_eq!(calculator.slots_per_epoch(), 32);
+        // Beacon slot 11003232, the first of epoch 343851, is slot 11003233.
+        assert_eq!(calculator.epoch_of_slot(11003232), 343850);
+        assert_eq!(calculator.epoch_of_slot(11003233), 343851);
+        assert_eq!(calculator.epoch_start_slot(343851), 11003233);
+        // Epoch 343851 starts at genesis 1606824023 + 11003232 * 12.
+        assert_eq!(
+            calculator.epoch_window(343851),
+            1738862807..1738862807 + 32 * 12
+        );
+        assert_eq!(calculator.epoch_containing(1738862806), Some(343850));
+        assert_eq!(calculator.epoch_containing(1738862807), Some(343851));
+    }
+
+    #[test]
+    fn load_slots_per_epoch() {
+        let mut source = std::collections::HashMap::from([
+            ("START_TIMESTAMP".to_owned(), "12".to_owned()),
+            ("SLOT_OFFSET".to_owned(), "0".to_owned()),
+            ("SLOT_DURATION".to_owned(), "12".to_owned()),
+        ]);
+        let calculator = SlotCalculator::from_source(&source).unwrap();
+        assert_eq!(calculator.slots_per_epoch(), DEFAULT_SLOTS_PER_EPOCH);
+
+        source.insert("SLOTS_PER_EPOCH".to_owned(), "8".to_owned());
+        let calculator = SlotCalculator::from_source(&source).unwrap();
+        assert_eq!(calculator.slots_per_epoch(), 8);
+
+        source.insert("SLOTS_PER_EPOCH".to_owned(), "0".to_owned());
+        assert!(SlotCalculator::from_source(&source).is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "slots per epoch must not be zero")]
+    fn zero_slots_per_epoch() {
+        let calculator = SlotCalculator {
+            slots_per_epoch: 0,
+            ..SlotCalculator::mainnet()
+        };
+        assert!(calculator.validate().is_err());
+
+        let _ = SlotCalculator::mainnet().with_slots_per_epoch(0);
+    }
+
+    const SCHEDULE: &[SlotSegment] = &[SlotSegment::new(120, 6), SlotSegment::new_ms(132_000, 500)];
+
+    #[test]
+    fn slot_schedule() {
+        // Slots 1 to 9 last 12s, slots 10 and 11 last 6s, and later slots
+        // last 500ms.
+        let calculator = SlotCalculator::new(12, 0, 12)
+            .with_slots_per_epoch(4)
+            .with_schedule(SCHEDULE);
+        assert!(calculator.validate().is_ok());
+
+        assert_eq!(calculator.slot_containing(119), Some(9));
+        assert_eq!(calculator.slot_containing(120), Some(10));
+        assert_eq!(calculator.slot_containing(131), Some(11));
+        assert_eq!(calculator.slot_containing_ms(132_499), Some(12));
+        assert_eq!(calculator.slot_containing_ms(132_500), Some(13));
+
+        assert_eq!(calculator.slot_window(9), 108..120);
+        assert_eq!(calculator.slot_window(10), 120..126);
+        assert_eq!(calculator.slot_window_ms(13), 132_500..133_000);
+        assert_eq!(calculator.point_within_slot(123), Some(3));
+        assert_eq!(calculator.point_within_slot_ms(132_750), Some(250));
+        assert_eq!(calculator.slot_starting_at(126), Some(11));
+        assert_eq!(calculator.slot_starting_at(129), None);
+        assert_eq!(calculator.slot_ending_at(120), Some(9));
+
+        assert_eq!(calculator.slot_duration_ms_of(9), 12_000);
+        assert_eq!(calculator.slot_duration_ms_of(10), 6_000);
+        assert_eq!(calculator.slot_duration_ms_of(12), 500);
+        assert_eq!(calculator.min_slot_duration_ms(), 500);
+
+        // Epoch 2 spans slots 9 to 12, across both forks.
+        assert_eq!(calculator.epoch_window_ms(2), 108_000..132_500);
+        assert_eq!(calculator.epoch_containing(107), Some(1));
+        assert_eq!(calculator.epoch_containing(131), Some(2));
+        assert_eq!(calculator.epoch_containing_ms(132_499), Some(2));
+        assert_eq!(calculator.epoch_containing_ms(132_500), Some(3));
+        assert_eq!(
+            calculator.current_slot_with(&MockClock::from_secs(126)),
+            Some(11)
+        );
+
+        // Forks must activate in order, at slot boundaries.
+        for schedule in [
+            &[SlotSegment::new(125, 6)][..],
+            &[SlotSegment::new(12, 6)],
+            &[SlotSegment::new(120, 6), SlotSegment::new(120, 4)],
+            &[SlotSegment::new(120, 0)],
+        ] {
+            assert!(
+                SlotCalculator::new(12, 0, 12)
+                    .with_schedule_from(schedule)
+                    .is_err(),
+                "{schedule:?}"
+            );
+        }
+        assert_eq!(
+            SlotCalculator::new(12, 0, 12)
+                .with_schedule_from(SCHEDULE)
+                .unwrap(),
+            calculator.with_slots_per_epoch(DEFAULT_SLOTS_PER_EPOCH)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid slot schedule")]
+    fn invalid_const_schedule() {
+        const UNALIGNED: &[SlotSegment] = &[SlotSegment::new(125, 6)];
+        let _ = SlotCalculator::new(12, 0, 12).with_schedule(UNALIGNED);
+    }
+
+    #[test]
+    fn load_slot_schedule() {
+        let mut source = std::collections::HashMap::from([
+            ("START_TIMESTAMP".to_owned(), "12".to_owned()),
+            ("SLOT_OFFSET".to_owned(), "0".to_owned()),
+            ("SLOT_DURATION".to_owned(), "12".to_owned()),
+            ("SLOT_SCHEDULE".to_owned(), "132=500ms,120=6".to_owned()),
+        ]);
+        let calculator = SlotCalculator::from_source(&source).unwrap();
+        assert_eq!(
+            calculator,
+            SlotCalculator::new(12, 0, 12).with_schedule(SCHEDULE)
+        );
+
+        source.insert("SLOT_SCHEDULE".to_owned(), "125=6".to_owned());
+        assert!(SlotCalculator::from_source(&source).is_err());
+
+        // Files use the same format, whether loaded as a `ConfigFile` or
+        // deserialized directly.
+        let contents = r#"
+            start_timestamp = 12
+            slot_offset = 0
+            slot_duration = 12
+
+            [slot_schedule]
+            120 = 6
+            132 = "500ms"
+        "#;
+        let expected = SlotCalculator::new(12, 0, 12).with_schedule(SCHEDULE);
+        let file = ConfigFile::from_toml(contents).unwrap();
+        let calculator =
+            SlotCalculator::from_source(&file.source_for(&SlotCalculator::inventory())).unwrap();
+        assert_eq!(calculator, expected);
+        assert_eq!(
+            toml::from_str::<SlotCalculator>(contents).unwrap(),
+            expected
+        );
+        let calculator: SlotCalculator = serde_json::from_str(
+            r#"{
+                "start_timestamp": 12,
+                "slot_offset": 0,
+                "slot_duration": 12,
+                "slot_schedule": { "120": "6s", "132": "500ms" }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(calculator, expected);
+        assert!(toml::from_str::<SlotCalculator>(&contents.replace("120 = 6", "125 = 6")).is_err());
+
+        assert_eq!(SlotCalculator::mainnet().schedule(), MAINNET_SLOT_SCHEDULE);
+    }
 }
@@ -0,0 +1,313 @@
+use super::SlotCalculator;
+use crate::utils::clock::{Clock, SharedClock, SystemClock};
+use core::{
+    future::{poll_fn, Future},
+    pin::Pin,
+    task::{ready, Context, Poll},
+    time::Duration,
+};
+use futures_util::Stream;
+use tokio::time::Sleep;
+
+/// The longest the ticker sleeps before re-checking the wall clock. Tokio
+/// timers use a monotonic clock, which may not advance while the system is
+/// suspended, so long sleeps could overshoot their target.
+const MAX_SLEEP: Duration = Duration::from_secs(1);
+
+/// A tick of a [`SlotTicker`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SlotTick {
+    /// The slot number.
+    pub slot: usize,
+    /// The start timestamp of the slot, in seconds.
+    pub slot_start: u64,
+    /// The start timestamp of the slot, in milliseconds.
+    pub slot_start_ms: u64,
+    /// The point within the slot at which the tick fired. This is zero for
+    /// ticks at the slot boundary.
+    pub offset: Duration,
+}
+
+/// A [`Stream`] of [`SlotTick`]s, firing at the start of each slot of a
+/// [`SlotCalculator`], and at any configured offsets within each slot.
+///
+/// Ticks are scheduled against the wall clock, which is the system clock
+/// unless set with [`SlotTicker::with_clock`]. If ticks are missed, e.g.
+/// because the consumer was slow or the system was suspended, the ticker
+/// skips them and yields only the most recent tick that is due.
+///
+/// The first tick is the first slot point after the ticker was created.
+///
+/// To tick at the query window of a [`SlotAuthzConfig`], use its
+/// `block_query_start` and `block_query_cutoff` as offsets.
+///
+/// ```no_run
+/// # use init4_bin_base::utils::calc::{SlotCalculator, SlotTicker};
+/// # use std::time::Duration;
+/// # async fn _main() {
+/// let mut ticker = SlotTicker::new(SlotCalculator::mainnet())
+///     .with_offsets([Duration::from_secs(1), Duration::from_secs(11)]);
+/// loop {
+///     let tick = ticker.tick().await;
+///     println!("slot {} + {:?}", tick.slot, tick.offset);
+/// }
+/// # }
+/// ```
+///
+/// [`SlotAuthzConfig`]: crate::perms::SlotAuthzConfig
+#[derive(Debug)]
+pub struct SlotTicker {
+    calc: SlotCalculator,
+
+    /// The clock used to determine the due ticks. Defaults to the system
+    /// clock.
+    clock: SharedClock,
+
+    /// The points within each slot at which to tick, in milliseconds, sorted
+    /// and starting with zero.
+    offsets: Vec<u64>,
+
+    /// The last tick yielded, or due when the ticker was created, as a slot
+    /// and an index into `offsets`.
+    last: Option<(usize, usize)>,
+
+    /// The timer, created on first poll, as it requires a tokio runtime.
+    sleep: Option<Pin<Box<Sleep>>>,
+}
+
+impl SlotTicker {
+    /// Create a ticker that fires at the start of each slot.
+    pub fn new(calc: SlotCalculator) -> Self {
+        let mut ticker = Self {
+            calc,
+            clock: SharedClock::system(),
+            offsets: vec![0],
+            last: None,
+            sleep: None,
+        };
+        ticker.last = ticker.due_at(ticker.clock.now_ms());
+        ticker
+    }
+
+    /// Use the `clock` to determine the due ticks, e.g. a [`MockClock`] in
+    /// tests. The first tick is the first slot point after the time of the
+    /// `clock`.
+    ///
+    /// The ticker still sleeps on tokio timers between checks of the clock,
+    /// for at most one second at a time.
+    ///
+    /// [`MockClock`]: crate::utils::clock::MockClock
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = SharedClock::new(clock);
+        self.last = self.due_at(self.clock.now_ms());
+        self
+    }
+
+    /// Also fire at the given offsets within each slot. Offsets that are not
+    /// within a slot's duration are skipped in that slot, e.g. in the shorter
+    /// slots after a slot-time hard fork.
+    pub fn with_offsets(mut self, offsets: impl IntoIterator<Item = Duration>) -> Self {
+        self.offsets
+            .extend(offsets.into_iter().map(|offset| offset.as_millis() as u64));
+        self.offsets.sort_unstable();
+        self.offsets.dedup();
+        self.last = self.due_at(self.clock.now_ms());
+        self
+    }
+
+    /// The slot calculator driving the ticker.
+    pub const fn calc(&self) -> &SlotCalculator {
+        &self.calc
+    }
+
+    /// Get the clock used to determine the due ticks.
+    pub const fn clock(&self) -> &SharedClock {
+        &self.clock
+    }
+
+    /// Wait for the next tick.
+    pub async fn tick(&mut self) -> SlotTick {
+        poll_fn(|cx| self.poll_tick(cx)).await
+    }
+
+    /// Poll for the next tick.
+    pub fn poll_tick(&mut self, cx: &mut Context<'_>) -> Poll<SlotTick> {
+        loop {
+            let now = self.clock.now_ms();
+            if let Some(due) = self.due_at(now).filter(|due| Some(*due) > self.last) {
+                self.last = Some(due);
+                return Poll::Ready(self.make_tick(due));
+            }
+
+            let target = match self.last {
+                // Wake at the next boundary at the latest, in case the next
+                // point is not within the slot.
+                Some(last) => self
+                    .point_ms(self.next_after(last))
+                    .min(self.calc.slot_end_ms(last.0)),
+                // Before the chain starts, wait for its first slot.
+                None => self.calc.start_timestamp_ms(),
+            };
+            let wait = Duration::from_millis(target.saturating_sub(now)).min(MAX_SLEEP);
+            let deadline = tokio::time::Instant::now() + wait;
+            let sleep = self
+                .sleep
+                .get_or_insert_with(|| Box::pin(tokio::time::sleep_until(deadline)));
+            sleep.as_mut().reset(deadline);
+            ready!(sleep.as_mut().poll(cx));
+        }
+    }
+
+    /// The latest tick due at the timestamp `now`, in milliseconds, or `None`
+    /// if the chain has not started.
+    fn due_at(&self, now: u64) -> Option<(usize, usize)> {
+        let slot = self.calc.slot_containing_ms(now)?;
+        let point = now - self.calc.slot_start_ms(slot);
+        let index = self.offsets.partition_point(|&offset| offset <= point) - 1;
+        Some((slot, index))
+    }
+
+    /// The tick following the `tick`, which may be past the end of the slot.
+    const fn next_after(&self, (slot, index): (usize, usize)) -> (usize, usize) {
+        if index + 1 < self.offsets.len() {
+            (slot, index + 1)
+        } else {
+            (slot + 1, 0)
+        }
+    }
+
+    /// The timestamp of the `tick`, in milliseconds.
+    fn point_ms(&self, (slot, index): (usize, usize)) -> u64 {
+        self.calc.slot_start_ms(slot) + self.offsets[index]
+    }
+
+    fn make_tick(&self, (slot, index): (usize, usize)) -> SlotTick {
+        SlotTick {
+            slot,
+            slot_start: self.calc.slot_start(slot),
+            slot_start_ms: self.calc.slot_start_ms(slot),
+            offset: Duration::from_millis(self.offsets[index]),
+        }
+    }
+}
+
+impl Stream for SlotTicker {
+    type Item = SlotTick;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().poll_tick(cx).map(Some)
+    }
+}
+
+impl SlotCalculator {
+    /// Sleep until `offset` into the given slot. Returns immediately if that
+    /// point has passed.
+    pub async fn sleep_until_slot_point(&self, slot: usize, offset: Duration) {
+        self.sleep_until_slot_point_with(&SystemClock, slot, offset)
+            .await
+    }
+
+    /// Sleep until `offset` into the given slot, according to the `clock`.
+    /// Returns immediately if that point has passed.
+    pub async fn sleep_until_slot_point_with(
+        &self,
+        clock: &dyn Clock,
+        slot: usize,
+        offset: Duration,
+    ) {
+        let target = self.slot_start_ms(slot) + offset.as_millis() as u64;
+        loop {
+            let now = clock.now_ms();
+            if now >= target {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(target - now).min(MAX_SLEEP)).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::utils::clock::MockClock;
+    use futures_util::{FutureExt, StreamExt};
+
+    #[test]
+    fn due_ticks() {
+        let ticker = SlotTicker::new(SlotCalculator::new(12, 0, 12))
+            .with_offsets([Duration::from_secs(2), Duration::from_secs(8)]);
+        assert_eq!(ticker.offsets, [0, 2000, 8000]);
+
+        assert_eq!(ticker.due_at(11_999), None);
+        assert_eq!(ticker.due_at(12_000), Some((1, 0)));
+        assert_eq!(ticker.due_at(13_999), Some((1, 0)));
+        assert_eq!(ticker.due_at(14_000), Some((1, 1)));
+        assert_eq!(ticker.due_at(23_999), Some((1, 2)));
+        // Ticks in skipped slots are not due.
+        assert_eq!(ticker.due_at(60_500), Some((5, 0)));
+
+        assert_eq!(ticker.next_after((1, 1)), (1, 2));
+        assert_eq!(ticker.next_after((1, 2)), (2, 0));
+        assert_eq!(ticker.point_ms((2, 1)), 26_000);
+    }
+
+    #[tokio::test]
+    async fn ticks_in_order() {
+        // 1.5s slots, starting at 0.
+        let calc = SlotCalculator::new_ms(0, 0, 1500);
+        let clock = MockClock::from_millis(100);
+        let mut ticker = SlotTicker::new(calc)
+            .with_offsets([Duration::from_millis(500)])
+            .with_clock(clock.clone());
+
+        // The tick at the start of slot 1 was due before the ticker started.
+        assert_eq!(ticker.tick().now_or_never(), None);
+
+        clock.set_ms(500);
+        let tick = ticker.next().now_or_never().flatten().unwrap();
+        assert_eq!(
+            (tick.slot, tick.slot_start_ms, tick.offset),
+            (1, 0, Duration::from_millis(500))
+        );
+        assert_eq!(ticker.tick().now_or_never(), None);
+
+        clock.set_ms(1500);
+        let tick = ticker.tick().now_or_never().unwrap();
+        assert_eq!(
+            (tick.slot, tick.slot_start, tick.slot_start_ms, tick.offset),
+            (2, 1, 1500, Duration::ZERO)
+        );
+
+        // Missed ticks are skipped.
+        clock.set_ms(5000);
+        let tick = ticker.tick().now_or_never().unwrap();
+        assert_eq!(
+            (tick.slot, tick.slot_start_ms, tick.offset),
+            (4, 4500, Duration::from_millis(500))
+        );
+        assert_eq!(ticker.tick().now_or_never(), None);
+    }
+
+    #[tokio::test]
+    async fn sleep_until_point_with_clock() {
+        let calc = SlotCalculator::new(0, 0, 12);
+        let clock = MockClock::from_secs(30);
+        tokio::time::timeout(
+            Duration::from_millis(100),
+            calc.sleep_until_slot_point_with(&clock, 2, Duration::from_secs(4)),
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn sleep_until_past_point() {
+        let calc = SlotCalculator::new(0, 0, 12);
+        tokio::time::timeout(
+            Duration::from_millis(100),
+            calc.sleep_until_slot_point(1, Duration::ZERO),
+        )
+        .await
+        .unwrap();
+    }
+}
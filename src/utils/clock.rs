@@ -0,0 +1,160 @@
+//! Wall-clock abstractions, which allow time-dependent logic to be tested
+//! deterministically.
+
+use core::{fmt, time::Duration};
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+/// A source of the current wall-clock time.
+///
+/// Slot calculations and builder permissioning read the time through a
+/// [`Clock`], so that tests can substitute a [`FixedClock`] or [`MockClock`]
+/// for the [`SystemClock`].
+pub trait Clock: fmt::Debug + Send + Sync {
+    /// The current time, in milliseconds since the Unix epoch.
+    fn now_ms(&self) -> u64;
+
+    /// The current time, in seconds since the Unix epoch.
+    fn now(&self) -> u64 {
+        self.now_ms() / 1000
+    }
+}
+
+impl<C: Clock + ?Sized> Clock for &C {
+    fn now_ms(&self) -> u64 {
+        (**self).now_ms()
+    }
+}
+
+impl<C: Clock + ?Sized> Clock for Box<C> {
+    fn now_ms(&self) -> u64 {
+        (**self).now_ms()
+    }
+}
+
+impl<C: Clock + ?Sized> Clock for Arc<C> {
+    fn now_ms(&self) -> u64 {
+        (**self).now_ms()
+    }
+}
+
+/// The system clock, via [`chrono::Utc::now`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_ms(&self) -> u64 {
+        chrono::Utc::now().timestamp_millis() as u64
+    }
+}
+
+/// A clock that always returns the same time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixedClock(u64);
+
+impl FixedClock {
+    /// Create a clock fixed at the given time, in seconds since the Unix
+    /// epoch.
+    pub const fn from_secs(timestamp: u64) -> Self {
+        Self(timestamp * 1000)
+    }
+
+    /// Create a clock fixed at the given time, in milliseconds since the Unix
+    /// epoch.
+    pub const fn from_millis(timestamp_ms: u64) -> Self {
+        Self(timestamp_ms)
+    }
+}
+
+impl Clock for FixedClock {
+    fn now_ms(&self) -> u64 {
+        self.0
+    }
+}
+
+/// A clock that is advanced manually. Clones share the same time, so a test
+/// can keep a clone to advance the clock used by the code under test.
+///
+/// ```
+/// # use init4_bin_base::utils::clock::{Clock, MockClock};
+/// # use std::time::Duration;
+/// let clock = MockClock::from_secs(12);
+/// let shared = clock.clone();
+/// shared.advance(Duration::from_millis(1500));
+/// assert_eq!(clock.now_ms(), 13_500);
+/// assert_eq!(clock.now(), 13);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MockClock(Arc<AtomicU64>);
+
+impl MockClock {
+    /// Create a clock starting at the given time, in seconds since the Unix
+    /// epoch.
+    pub fn from_secs(timestamp: u64) -> Self {
+        Self::from_millis(timestamp * 1000)
+    }
+
+    /// Create a clock starting at the given time, in milliseconds since the
+    /// Unix epoch.
+    pub fn from_millis(timestamp_ms: u64) -> Self {
+        Self(Arc::new(AtomicU64::new(timestamp_ms)))
+    }
+
+    /// Set the time, in seconds since the Unix epoch.
+    pub fn set(&self, timestamp: u64) {
+        self.set_ms(timestamp * 1000);
+    }
+
+    /// Set the time, in milliseconds since the Unix epoch.
+    pub fn set_ms(&self, timestamp_ms: u64) {
+        self.0.store(timestamp_ms, Ordering::SeqCst);
+    }
+
+    /// Advance the time by `duration`.
+    pub fn advance(&self, duration: Duration) {
+        self.0
+            .fetch_add(duration.as_millis() as u64, Ordering::SeqCst);
+    }
+}
+
+impl Clock for MockClock {
+    fn now_ms(&self) -> u64 {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// A shared, type-erased [`Clock`], which defaults to the [`SystemClock`].
+#[derive(Clone, Default)]
+pub struct SharedClock(Option<Arc<dyn Clock>>);
+
+impl fmt::Debug for SharedClock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.0 {
+            Some(clock) => f.debug_tuple("SharedClock").field(clock).finish(),
+            None => f.debug_tuple("SharedClock").field(&SystemClock).finish(),
+        }
+    }
+}
+
+impl SharedClock {
+    /// The [`SystemClock`].
+    pub const fn system() -> Self {
+        Self(None)
+    }
+
+    /// Wrap the `clock`.
+    pub fn new(clock: impl Clock + 'static) -> Self {
+        Self(Some(Arc::new(clock)))
+    }
+}
+
+impl Clock for SharedClock {
+    fn now_ms(&self) -> u64 {
+        match &self.0 {
+            Some(clock) => clock.now_ms(),
+            None => SystemClock.now_ms(),
+        }
+    }
+}
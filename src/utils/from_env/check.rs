@@ -0,0 +1,318 @@
+use super::{source::file_var, EnvItemInfo, EnvSource, FromEnv, FromEnvErr, ProcessEnv};
+use core::fmt::{self, Display, Formatter};
+use std::{collections::HashSet, env::VarError};
+
+/// A problem found by [`ConfigCheck`].
+#[derive(Debug)]
+pub enum Diagnostic {
+    /// A required variable is not set.
+    Missing {
+        /// The name of the variable.
+        var: String,
+        /// The item describing the variable, if it is in the inventory.
+        item: Option<&'static EnvItemInfo>,
+    },
+    /// A required variable is set to an empty string.
+    Empty {
+        /// The name of the variable.
+        var: String,
+        /// The item describing the variable, if it is in the inventory.
+        item: Option<&'static EnvItemInfo>,
+    },
+    /// A variable is set, but could not be parsed or is otherwise invalid.
+    Invalid {
+        /// The error loading the variable.
+        error: FromEnvErr,
+        /// The item describing the variable, if it is in the inventory.
+        item: Option<&'static EnvItemInfo>,
+    },
+    /// A variable is set, but is not read, because its conditions do not
+    /// hold, e.g. it belongs to a variant of an enum config that is not
+    /// selected.
+    Unused {
+        /// The item describing the variable.
+        item: &'static EnvItemInfo,
+    },
+    /// A variable matching one of the configured prefixes is set, but is not
+    /// in the inventory. This is usually a typo.
+    Unknown {
+        /// The name of the variable.
+        var: String,
+        /// The closest variable in the inventory, if any is close.
+        suggestion: Option<&'static str>,
+    },
+}
+
+impl Diagnostic {
+    /// The item describing the variable, if it is in the inventory.
+    pub const fn item(&self) -> Option<&'static EnvItemInfo> {
+        match self {
+            Self::Missing { item, .. } | Self::Empty { item, .. } | Self::Invalid { item, .. } => {
+                *item
+            }
+            Self::Unused { item } => Some(item),
+            Self::Unknown { .. } => None,
+        }
+    }
+
+    /// Classify a loading error. Some [`FromEnvVar`] impls report unset
+    /// variables as empty, so the `source` is checked to tell them apart.
+    ///
+    /// [`FromEnvVar`]: super::FromEnvVar
+    fn from_err(
+        error: FromEnvErr,
+        item: Option<&'static EnvItemInfo>,
+        source: &dyn EnvSource,
+    ) -> Self {
+        match error {
+            FromEnvErr::EnvError(var, VarError::NotPresent) => Self::Missing { var, item },
+            FromEnvErr::Empty(var) if source.get(&var) == Err(VarError::NotPresent) => {
+                Self::Missing { var, item }
+            }
+            FromEnvErr::Empty(var) => Self::Empty { var, item },
+            error => Self::Invalid { error, item },
+        }
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Missing { var, .. } => write!(f, "missing required environment variable {var}"),
+            Self::Empty { var, .. } => write!(f, "environment variable {var} is empty"),
+            Self::Invalid { error, .. } => Display::fmt(error, f),
+            Self::Unused { item } => {
+                let conditions = item.conditions.iter().map(ToString::to_string);
+                write!(
+                    f,
+                    "environment variable {} is set but unused, it is only read if {}",
+                    item.var,
+                    conditions.collect::<Vec<_>>().join(" and ")
+                )
+            }
+            Self::Unknown { var, suggestion } => {
+                write!(f, "unknown environment variable {var}")?;
+                if let Some(suggestion) = suggestion {
+                    write!(f, ", did you mean {suggestion}?")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// The result of a [`ConfigCheck`].
+#[derive(Debug, Default)]
+pub struct ConfigReport {
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl ConfigReport {
+    /// True if no problems were found.
+    pub const fn is_ok(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+
+    /// The problems found, in inventory order, followed by unknown
+    /// variables.
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+}
+
+impl IntoIterator for ConfigReport {
+    type Item = Diagnostic;
+    type IntoIter = std::vec::IntoIter<Diagnostic>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.diagnostics.into_iter()
+    }
+}
+
+impl Display for ConfigReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let count = self.diagnostics.len();
+        if count == 0 {
+            return write!(f, "configuration OK");
+        }
+        let plural = if count == 1 { "" } else { "s" };
+        write!(f, "{count} problem{plural} found in configuration:")?;
+        for diagnostic in &self.diagnostics {
+            write!(f, "\n  - {diagnostic}")?;
+            if let Some(item) = diagnostic.item() {
+                write!(f, "\n      {}: {}", item.var, item.description)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A preflight check of a [`FromEnv`] config.
+///
+/// Unlike [`FromEnv::check_inventory`], which only checks that required
+/// variables are present, this loads the config, so that every variable is
+/// parsed and validated, and reports:
+///
+/// - [`Diagnostic::Missing`] and [`Diagnostic::Empty`] required variables.
+/// - [`Diagnostic::Invalid`] variables, with the reason.
+/// - [`Diagnostic::Unused`] variables, which are set but ignored because
+///   their conditions do not hold.
+/// - [`Diagnostic::Unknown`] variables, which match one of the configured
+///   prefixes but are not in the inventory, e.g. `OAUTH_CLIENTID`.
+///
+/// Unknown variables are only detected in sources that can list their
+/// variables. See [`EnvSource::vars`].
+///
+/// ```
+/// # use init4_bin_base::utils::{from_env::{ConfigCheck, Diagnostic}, otlp::OtelConfig};
+/// # use std::collections::HashMap;
+/// let source = HashMap::from([
+///     ("OTEL_EXPORTER_OTLP_ENDPOINT".to_owned(), "not a url".to_owned()),
+///     ("OTEL_LEVLE".to_owned(), "debug".to_owned()),
+/// ]);
+/// let report = ConfigCheck::new()
+///     .with_prefix("OTEL_")
+///     .check::<OtelConfig>(&source);
+/// assert!(!report.is_ok());
+/// assert!(matches!(report.diagnostics()[0], Diagnostic::Invalid { .. }));
+/// assert_eq!(
+///     report.diagnostics()[1].to_string(),
+///     "unknown environment variable OTEL_LEVLE, did you mean OTEL_LEVEL?"
+/// );
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConfigCheck {
+    prefixes: Vec<String>,
+}
+
+impl ConfigCheck {
+    /// Create a check with no prefixes, which does not detect unknown
+    /// variables.
+    pub const fn new() -> Self {
+        Self {
+            prefixes: Vec::new(),
+        }
+    }
+
+    /// Report variables that start with the `prefix`, but are not in the
+    /// inventory, as [`Diagnostic::Unknown`].
+    pub fn with_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefixes.push(prefix.into());
+        self
+    }
+
+    /// Report unknown variables that share a prefix with a variable in the
+    /// inventory of `T`, i.e. its segment up to and including the first `_`,
+    /// e.g. `OAUTH_` for `OAUTH_CLIENT_ID`. Variables without a `_` add no
+    /// prefix.
+    pub fn with_inventory_prefixes<T: FromEnv>(mut self) -> Self {
+        for item in T::inventory() {
+            let Some(end) = item.var.find('_') else {
+                continue;
+            };
+            let prefix = &item.var[..=end];
+            if !self.prefixes.iter().any(|known| known == prefix) {
+                self.prefixes.push(prefix.to_owned());
+            }
+        }
+        self
+    }
+
+    /// Check the config `T` in the process environment.
+    pub fn check_env<T: FromEnv>(&self) -> ConfigReport {
+        self.check::<T>(&ProcessEnv)
+    }
+
+    /// Check the config `T` in the `source`.
+    pub fn check<T: FromEnv>(&self, source: &dyn EnvSource) -> ConfigReport {
+        let inventory = T::inventory();
+        let mut diagnostics: Vec<_> = match T::from_source_accumulate(source) {
+            Ok(_) => Vec::new(),
+            Err(errors) => errors
+                .into_iter()
+                .map(|(error, item)| Diagnostic::from_err(error, item, source))
+                .collect(),
+        };
+
+        let is_set = |var: &str| source.get(var).is_ok();
+        let active: HashSet<_> = inventory
+            .iter()
+            .filter(|item| item.is_active(source))
+            .map(|item| item.var)
+            .collect();
+        let mut reported = HashSet::new();
+        for &item in &inventory {
+            let set = is_set(item.var) || item.aliases.iter().any(|alias| is_set(alias));
+            if set && !active.contains(item.var) && reported.insert(item.var) {
+                diagnostics.push(Diagnostic::Unused { item });
+            }
+        }
+
+        if self.prefixes.is_empty() {
+            return ConfigReport { diagnostics };
+        }
+
+        let known: HashSet<String> = inventory
+            .iter()
+            .flat_map(|item| {
+                let file = item.file.then(|| file_var(item.var));
+                [item.var.to_owned()]
+                    .into_iter()
+                    .chain(item.aliases.iter().map(|alias| (*alias).to_owned()))
+                    .chain(item.conditions.iter().map(|c| c.var.to_owned()))
+                    .chain(file)
+            })
+            .collect();
+
+        let mut vars = source.vars();
+        vars.sort_unstable();
+        for var in vars {
+            let matches_prefix = self.prefixes.iter().any(|prefix| var.starts_with(prefix));
+            if matches_prefix && !known.contains(&var) {
+                let suggestion = suggest(&var, &inventory);
+                diagnostics.push(Diagnostic::Unknown { var, suggestion });
+            }
+        }
+
+        ConfigReport { diagnostics }
+    }
+}
+
+/// Find the variable in the inventory closest to `var`, if it is within a
+/// few edits.
+fn suggest(var: &str, inventory: &[&'static EnvItemInfo]) -> Option<&'static str> {
+    inventory
+        .iter()
+        .map(|item| (edit_distance(var, item.var), item.var))
+        .filter(|(distance, _)| *distance <= 2)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, var)| var)
+}
+
+/// The Levenshtein distance between `a` and `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut cur = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = prev[j] + usize::from(ca != *cb);
+            cur[j + 1] = substitution.min(prev[j + 1] + 1).min(cur[j] + 1);
+        }
+        prev = cur;
+    }
+    prev[b.len()]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn distances() {
+        assert_eq!(edit_distance("OAUTH_CLIENTID", "OAUTH_CLIENT_ID"), 1);
+        assert_eq!(edit_distance("METRICS_PROT", "METRICS_PORT"), 2);
+        assert_eq!(edit_distance("", "ABC"), 3);
+        assert_eq!(edit_distance("SAME", "SAME"), 0);
+    }
+}
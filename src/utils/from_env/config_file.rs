@@ -0,0 +1,212 @@
+use super::{
+    EnvItemInfo, EnvSource, FromEnv, FromEnvErr, LayeredSource, ProcessEnv, DEFAULT_SEPARATOR,
+};
+use serde::{de::Error as _, Deserialize, Deserializer};
+use serde_json::Value;
+use std::{collections::HashMap, path::Path};
+
+/// The environment variable that names the config file loaded by [`init`].
+///
+/// [`init`]: crate::init
+pub const CONFIG_FILE_VAR: &str = "CONFIG_FILE";
+
+/// Errors that can occur when loading a [`ConfigFile`].
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigFileError {
+    /// The file could not be read.
+    #[error("failed to read config file: {0}")]
+    Io(#[from] std::io::Error),
+    /// The file is not valid TOML.
+    #[error("invalid TOML config file: {0}")]
+    Toml(#[from] toml::de::Error),
+    /// The file is not valid JSON.
+    #[error("invalid JSON config file: {0}")]
+    Json(#[from] serde_json::Error),
+    /// The file extension is not `.toml` or `.json`.
+    #[error("unsupported config file extension, expected .toml or .json: {0}")]
+    UnknownFormat(String),
+}
+
+/// A TOML or JSON config file.
+///
+/// Values are located in the file by the [`EnvItemInfo::key`] of each item in
+/// a config's inventory. The derive macro uses field names as keys, with
+/// nested [`FromEnv`] structs as nested tables, i.e. the same shape as a
+/// `serde` derive. For example, the [`Builders`] config may be loaded from:
+///
+/// ```toml
+/// builders = ["alice", "bob"]
+///
+/// [config]
+/// block_query_start = 1
+/// block_query_cutoff = 11
+///
+/// [config.calc]
+/// chain_name = "parmigiana"
+/// ```
+///
+/// Values are converted to the string an environment variable would hold.
+/// Strings are used as-is, numbers and booleans are formatted, arrays are
+/// joined with the item's separator, and tables are converted to `key=value`
+/// entries, as parsed by the [`FromEnvVar`] impls for maps.
+///
+/// [`FromEnvVar`]: super::FromEnvVar
+///
+/// [`Builders`]: crate::perms::Builders
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConfigFile {
+    value: Value,
+}
+
+impl ConfigFile {
+    /// Read and parse the file at the given path. The format is determined by
+    /// the file extension, which must be `.toml` or `.json`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigFileError> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Self::from_toml(&contents),
+            Some("json") => Self::from_json(&contents),
+            _ => Err(ConfigFileError::UnknownFormat(path.display().to_string())),
+        }
+    }
+
+    /// Parse the contents of a TOML file.
+    pub fn from_toml(contents: &str) -> Result<Self, ConfigFileError> {
+        Ok(Self::from_value(toml::from_str(contents)?))
+    }
+
+    /// Parse the contents of a JSON file.
+    pub fn from_json(contents: &str) -> Result<Self, ConfigFileError> {
+        Ok(Self::from_value(serde_json::from_str(contents)?))
+    }
+
+    /// Wrap an already-parsed value.
+    pub const fn from_value(value: Value) -> Self {
+        Self { value }
+    }
+
+    /// Get the value at the dot-separated `key`, if any.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        key.split('.')
+            .try_fold(&self.value, |value, segment| value.get(segment))
+    }
+
+    /// Flatten the file into an [`EnvSource`] keyed by the environment
+    /// variable names of the items in the `inventory`.
+    pub fn source_for(&self, inventory: &[&'static EnvItemInfo]) -> HashMap<String, String> {
+        inventory
+            .iter()
+            .filter_map(|item| {
+                let separator = item.separator.unwrap_or(DEFAULT_SEPARATOR);
+                let value = value_to_string(self.get(item.key)?, separator)?;
+                Some((item.var.to_owned(), value))
+            })
+            .collect()
+    }
+
+    /// Get the source used to load `T` by [`init`].
+    ///
+    /// If the [`CONFIG_FILE_VAR`] environment variable is set, this loads the
+    /// config file it names, and layers the process environment on top of
+    /// it, so that environment variables override individual keys in the
+    /// file. Otherwise, the source is just the process environment.
+    ///
+    /// [`init`]: crate::init
+    pub fn env_source<T: FromEnv>() -> Result<LayeredSource, FromEnvErr> {
+        let source = LayeredSource::new().with_layer(ProcessEnv);
+        let path = match ProcessEnv.get(CONFIG_FILE_VAR) {
+            Ok(path) if !path.is_empty() => path,
+            _ => return Ok(source),
+        };
+
+        let file = Self::load(path).map_err(|err| FromEnvErr::parse_error(CONFIG_FILE_VAR, err))?;
+        Ok(source.with_layer(file.source_for(&T::inventory())))
+    }
+
+    /// Deserialize `T` from a config file value, using the config keys in its
+    /// inventory. This backs the `serde::Deserialize` impl generated by
+    /// `#[from_env(deserialize)]`.
+    pub fn deserialize_config<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        T: FromEnv,
+        D: Deserializer<'de>,
+    {
+        let file = Self::from_value(Value::deserialize(deserializer)?);
+        T::from_source_accumulate(&file.source_for(&T::inventory())).map_err(D::Error::custom)
+    }
+}
+
+/// Convert a config file value to the string an environment variable would
+/// hold, joining lists with the `separator`. Returns `None` for `null`.
+fn value_to_string(value: &Value, separator: &str) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::String(s) => Some(s.clone()),
+        Value::Array(values) => Some(
+            values
+                .iter()
+                .filter_map(|value| value_to_string(value, separator))
+                .collect::<Vec<_>>()
+                .join(separator),
+        ),
+        Value::Object(entries) => Some(
+            entries
+                .iter()
+                .filter_map(|(key, value)| {
+                    Some(format!("{key}={}", value_to_string(value, separator)?))
+                })
+                .collect::<Vec<_>>()
+                .join(separator),
+        ),
+        other => Some(other.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::utils::{calc::SlotCalculator, metrics::MetricsConfig};
+
+    #[test]
+    fn flatten_values() {
+        let file = ConfigFile::from_toml(
+            r#"
+            name = "config"
+            list = ["a", "b", 3]
+            flag = true
+
+            [nested.deeper]
+            number = 12
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(file.get("nested.deeper.number"), Some(&Value::from(12)));
+        assert_eq!(file.get("nested.missing"), None);
+        let to_string = |key| value_to_string(file.get(key).unwrap(), ",").unwrap();
+        assert_eq!(to_string("list"), "a,b,3");
+        assert_eq!(to_string("flag"), "true");
+        assert_eq!(to_string("name"), "config");
+        assert_eq!(to_string("nested.deeper"), "number=12");
+        assert_eq!(
+            value_to_string(file.get("list").unwrap(), ";").unwrap(),
+            "a;b;3"
+        );
+    }
+
+    #[test]
+    fn hand_written_keys() {
+        let file = ConfigFile::from_json(
+            r#"{ "start_timestamp": 10, "slot_offset": 2, "slot_duration": 4, "port": 9999 }"#,
+        )
+        .unwrap();
+
+        let source = file.source_for(&SlotCalculator::inventory());
+        let calc = SlotCalculator::from_source(&source).unwrap();
+        assert_eq!(calc, SlotCalculator::new(10, 2, 4));
+
+        let source = file.source_for(&MetricsConfig::inventory());
+        assert_eq!(MetricsConfig::from_source(&source).unwrap().port, 9999);
+    }
+}
@@ -0,0 +1,549 @@
+use super::{source::file_var, EnvCondition, EnvItemInfo, FromEnv};
+use core::fmt::Write;
+use serde::{ser::Error as _, Serialize, Serializer};
+use serde_json::{json, Map, Value};
+
+/// The JSON Schema dialect of [`EnvDocs::to_json_schema`].
+const JSON_SCHEMA_DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// Documentation for the environment variables used by a config, generated
+/// from its [`FromEnv::inventory`].
+///
+/// Variables are grouped by the config struct that declares them, in the
+/// order in which they appear in the inventory. Variables that appear more
+/// than once, e.g. `CHAIN_NAME`, which is read by both the [`SlotCalculator`]
+/// and the [`SignetSystemConstants`], are documented only once, in the group
+/// in which they first appear. See [`EnvVarDoc`] for how their entries are
+/// merged.
+///
+/// ```
+/// # use init4_bin_base::utils::{from_env::EnvDocs, metrics::MetricsConfig};
+/// let docs = EnvDocs::of::<MetricsConfig>();
+/// assert!(docs.to_markdown().contains("| `METRICS_PORT` |"));
+/// assert!(docs.to_env_example().contains("#METRICS_PORT=9000"));
+/// assert_eq!(docs.to_json_schema()["properties"]["METRICS_PORT"]["default"], "9000");
+/// assert!(docs.to_helm_env("secrets").contains("#   value: \"9000\""));
+/// ```
+///
+/// [`SlotCalculator`]: crate::utils::calc::SlotCalculator
+/// [`SignetSystemConstants`]: signet_constants::SignetSystemConstants
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct EnvDocs {
+    groups: Vec<EnvGroup>,
+}
+
+/// The environment variables declared by a single config struct. See
+/// [`EnvDocs`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EnvGroup {
+    /// The name of the config struct.
+    pub name: &'static str,
+    /// The environment variables, in inventory order.
+    pub vars: Vec<EnvVarDoc>,
+}
+
+/// A documented environment variable, merged from every inventory entry that
+/// reads it.
+///
+/// The variable is required if any entry requires it, under the union of the
+/// conditions of those entries, e.g. `SIGNER_KEY` is required if
+/// `SIGNER_TYPE=local` or `SIGNER_TYPE=aws`. Its description, type and
+/// default are those of the first entry.
+///
+/// It is serialized as its first entry, with `optional` and `conditions`
+/// replaced by the merged values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnvVarDoc {
+    /// The first inventory entry for the variable.
+    pub item: &'static EnvItemInfo,
+    /// Whether every entry is optional.
+    pub optional: bool,
+    /// The sets of conditions, any of which makes the variable required, or
+    /// read if it is optional. The conditions within a set must all hold.
+    /// Empty if the variable is unconditional.
+    pub conditions: Vec<&'static [EnvCondition]>,
+    /// Whether any entry is a secret. See [`EnvItemInfo::is_secret`].
+    pub secret: bool,
+}
+
+impl EnvVarDoc {
+    /// Merge the inventory `entries` for a single variable.
+    fn merge(entries: &[&'static EnvItemInfo]) -> Self {
+        let item = entries[0];
+        let optional = entries.iter().all(|entry| entry.optional);
+        // Optional entries do not affect when a required variable is
+        // required, and an unconditional entry overrides conditional ones.
+        let mut conditions: Vec<&'static [EnvCondition]> = Vec::new();
+        for entry in entries.iter().filter(|entry| optional || !entry.optional) {
+            if entry.conditions.is_empty() {
+                conditions.clear();
+                break;
+            }
+            if !conditions.contains(&entry.conditions) {
+                conditions.push(entry.conditions);
+            }
+        }
+
+        Self {
+            item,
+            optional,
+            conditions,
+            secret: entries.iter().any(|entry| entry.is_secret()),
+        }
+    }
+}
+
+impl Serialize for EnvVarDoc {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut value = serde_json::to_value(self.item).map_err(S::Error::custom)?;
+        value["optional"] = self.optional.into();
+        value["conditions"] = serde_json::to_value(&self.conditions).map_err(S::Error::custom)?;
+        value.serialize(serializer)
+    }
+}
+
+impl EnvDocs {
+    /// Create documentation for the items in the inventory.
+    pub fn new(inventory: impl IntoIterator<Item = &'static EnvItemInfo>) -> Self {
+        // The entries of each variable, in order of first appearance.
+        let mut entries: Vec<Vec<&'static EnvItemInfo>> = Vec::new();
+        for item in inventory {
+            match entries
+                .iter_mut()
+                .find(|entries| entries[0].var == item.var)
+            {
+                Some(entries) => entries.push(item),
+                None => entries.push(vec![item]),
+            }
+        }
+
+        let mut groups: Vec<EnvGroup> = Vec::new();
+        for entries in entries {
+            let var = EnvVarDoc::merge(&entries);
+            match groups.iter_mut().find(|group| group.name == var.item.group) {
+                Some(group) => group.vars.push(var),
+                None => groups.push(EnvGroup {
+                    name: var.item.group,
+                    vars: vec![var],
+                }),
+            }
+        }
+
+        Self { groups }
+    }
+
+    /// Create documentation for the environment variables used by `T`.
+    pub fn of<T: FromEnv>() -> Self {
+        Self::new(T::inventory())
+    }
+
+    /// Get the groups of environment variables.
+    pub fn groups(&self) -> &[EnvGroup] {
+        &self.groups
+    }
+
+    /// Iterate over the documented environment variables.
+    pub fn vars(&self) -> impl Iterator<Item = &EnvVarDoc> + '_ {
+        self.groups.iter().flat_map(|group| group.vars.iter())
+    }
+
+    /// Render the documentation as Markdown, with a table per group.
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::new();
+        for group in &self.groups {
+            if !out.is_empty() {
+                out.push('\n');
+            }
+            writeln!(out, "## {}\n", group.name).unwrap();
+            out.push_str("| Variable | Type | Required | Default | Description |\n");
+            out.push_str("| --- | --- | --- | --- | --- |\n");
+            for var_doc in &group.vars {
+                let item = var_doc.item;
+                let mut var = if item.file {
+                    format!("`{}` or `{}`", item.var, file_var(item.var))
+                } else {
+                    format!("`{}`", item.var)
+                };
+                if !item.aliases.is_empty() {
+                    let aliases = item.aliases.iter().map(|alias| format!("`{alias}`"));
+                    write!(
+                        var,
+                        " (deprecated: {})",
+                        aliases.collect::<Vec<_>>().join(", ")
+                    )
+                    .unwrap();
+                }
+                let mut required = if var_doc.optional { "no" } else { "yes" }.to_owned();
+                if !var_doc.optional && !var_doc.conditions.is_empty() {
+                    write!(required, ", if {}", conditions(&var_doc.conditions)).unwrap();
+                }
+                let default = item
+                    .default
+                    .map(|default| format!("`{}`", escape_cell(default)))
+                    .unwrap_or_default();
+                writeln!(
+                    out,
+                    "| {var} | `{}` | {required} | {default} | {} |",
+                    escape_cell(item.type_name),
+                    escape_cell(&description(item)),
+                )
+                .unwrap();
+            }
+        }
+        out
+    }
+
+    /// Render the documentation as pretty-printed JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("serializing to a string cannot fail")
+    }
+
+    /// Render the documentation as an annotated `.env.example` file. Required
+    /// variables are left empty, optional variables are commented out and set
+    /// to their default, if any.
+    pub fn to_env_example(&self) -> String {
+        let mut out = String::new();
+        for group in &self.groups {
+            if !out.is_empty() {
+                out.push('\n');
+            }
+            writeln!(out, "# ---- {} ----", group.name).unwrap();
+            for var in &group.vars {
+                let item = var.item;
+                out.push('\n');
+                for line in description(item).lines() {
+                    writeln!(out, "# {line}").unwrap();
+                }
+                let required = if var.optional { "optional" } else { "required" };
+                write!(out, "# Type: {}, {required}", item.type_name).unwrap();
+                if !var.conditions.is_empty() {
+                    write!(out, " if {}", conditions(&var.conditions)).unwrap();
+                }
+                out.push('\n');
+                if item.file {
+                    writeln!(
+                        out,
+                        "# May instead be read from the file at {}",
+                        file_var(item.var)
+                    )
+                    .unwrap();
+                }
+                if !item.aliases.is_empty() {
+                    writeln!(out, "# Deprecated aliases: {}", item.aliases.join(", ")).unwrap();
+                }
+                let comment = if var.optional { "#" } else { "" };
+                writeln!(
+                    out,
+                    "{comment}{}={}",
+                    item.var,
+                    item.default.unwrap_or_default()
+                )
+                .unwrap();
+            }
+        }
+        out
+    }
+
+    /// Render the documentation as a [JSON Schema] for an object mapping
+    /// environment variable names to their string values, e.g. the `env` of
+    /// a deployment manifest.
+    ///
+    /// Each variable is described by a property, which records its
+    /// description, default and accepted values, as well as the following
+    /// extensions:
+    /// - `x-type`: the name of the type the value is parsed into.
+    /// - `x-group`: the name of the config struct that declares it.
+    /// - `x-secret`: whether the value is a secret, see
+    ///   [`EnvItemInfo::is_secret`]. Secret properties are also marked
+    ///   `writeOnly`.
+    /// - `x-separator`: the separator of list elements, if not the default.
+    ///
+    /// Deprecated aliases and `_FILE` variables have properties of their own.
+    /// Required variables must be set, or be provided by an alias or `_FILE`
+    /// variable, only if their conditions hold. Variables required under
+    /// several sets of conditions, e.g. by several variants of an enum
+    /// config, have an `if`/`then` rule for each set.
+    ///
+    /// [JSON Schema]: https://json-schema.org/
+    pub fn to_json_schema(&self) -> Value {
+        let mut properties = Map::new();
+        let mut required = Vec::new();
+        let mut all_of = Vec::new();
+
+        for var in self.vars() {
+            let item = var.item;
+            let mut property = json!({
+                "type": "string",
+                "description": description(item),
+                "x-type": item.type_name,
+                "x-group": item.group,
+                "x-secret": var.secret,
+            });
+            if let Some(default) = item.default {
+                property["default"] = default.into();
+            }
+            if !item.values.is_empty() {
+                property["enum"] = item.values.into();
+            }
+            if let Some(pattern) = integer_pattern(item.type_name) {
+                property["pattern"] = pattern.into();
+            }
+            if let Some(separator) = item.separator {
+                property["x-separator"] = separator.into();
+            }
+            if var.secret {
+                property["writeOnly"] = true.into();
+            }
+
+            // Names by which the variable may be provided.
+            let mut names = vec![item.var.to_owned()];
+            if item.file {
+                let file = file_var(item.var);
+                properties.insert(
+                    file.clone(),
+                    json!({
+                        "type": "string",
+                        "description": format!("Path to a file containing the value of {}.", item.var),
+                        "x-group": item.group,
+                    }),
+                );
+                names.push(file);
+            }
+            for alias in item.aliases {
+                let mut alias_property = property.clone();
+                alias_property["deprecated"] = true.into();
+                alias_property["description"] = format!("Deprecated alias of {}.", item.var).into();
+                properties.insert((*alias).to_owned(), alias_property);
+                names.push((*alias).to_owned());
+            }
+            properties.insert(item.var.to_owned(), property);
+
+            if var.optional {
+                continue;
+            }
+            let requirement = if names.len() == 1 {
+                json!({ "required": names })
+            } else {
+                json!({ "anyOf": names.iter().map(|name| json!({ "required": [name] })).collect::<Vec<_>>() })
+            };
+            match (var.conditions.is_empty(), names.len()) {
+                (true, 1) => required.push(item.var),
+                (true, _) => all_of.push(requirement),
+                // One rule per set of conditions, as the variable is required
+                // if any of them holds.
+                (false, _) => all_of.extend(var.conditions.iter().map(|set| {
+                    let conditions: Map<_, _> = set
+                        .iter()
+                        .map(|condition| {
+                            (
+                                condition.var.to_owned(),
+                                json!({ "const": condition.value }),
+                            )
+                        })
+                        .collect();
+                    let condition_vars: Vec<_> =
+                        set.iter().map(|condition| condition.var).collect();
+                    json!({
+                        "if": { "properties": conditions, "required": condition_vars },
+                        "then": requirement,
+                    })
+                })),
+            }
+        }
+
+        let mut schema = json!({
+            "$schema": JSON_SCHEMA_DIALECT,
+            "type": "object",
+            "properties": properties,
+            "required": required,
+        });
+        if !all_of.is_empty() {
+            schema["allOf"] = all_of.into();
+        }
+        schema
+    }
+
+    /// Render the documentation as the `env:` section of a Kubernetes
+    /// container spec, e.g. for a Helm chart's values. Required variables are
+    /// set to their default, or left empty, and optional variables are
+    /// commented out. Secret variables, see [`EnvItemInfo::is_secret`], are
+    /// read from the key with the same name in the Kubernetes secret named
+    /// `secret_name`.
+    pub fn to_helm_env(&self, secret_name: &str) -> String {
+        let mut out = String::from("env:\n");
+        for group in &self.groups {
+            writeln!(out, "  # ---- {} ----", group.name).unwrap();
+            for var in &group.vars {
+                let item = var.item;
+                for line in description(item).lines() {
+                    writeln!(out, "  # {line}").unwrap();
+                }
+                let required = if var.optional { "optional" } else { "required" };
+                write!(out, "  # Type: {}, {required}", item.type_name).unwrap();
+                if !var.conditions.is_empty() {
+                    write!(out, " if {}", conditions(&var.conditions)).unwrap();
+                }
+                out.push('\n');
+
+                let comment = if var.optional { "# " } else { "" };
+                writeln!(out, "  {comment}- name: {}", item.var).unwrap();
+                if var.secret {
+                    writeln!(out, "  {comment}  valueFrom:").unwrap();
+                    writeln!(out, "  {comment}    secretKeyRef:").unwrap();
+                    writeln!(out, "  {comment}      name: {}", yaml_str(secret_name)).unwrap();
+                    writeln!(out, "  {comment}      key: {}", item.var).unwrap();
+                } else {
+                    let value = yaml_str(item.default.unwrap_or_default());
+                    writeln!(out, "  {comment}  value: {value}").unwrap();
+                }
+            }
+        }
+        out
+    }
+}
+
+/// A regex matching the values of integer types, if the `type_name`, or the
+/// type in an `Option`, is one.
+fn integer_pattern(type_name: &str) -> Option<&'static str> {
+    let type_name = type_name
+        .strip_prefix("Option<")
+        .and_then(|inner| inner.strip_suffix('>'))
+        .unwrap_or(type_name);
+    match type_name {
+        "u8" | "u16" | "u32" | "u64" | "u128" | "usize" => Some("^[0-9]+$"),
+        "i8" | "i16" | "i32" | "i64" | "i128" | "isize" => Some("^-?[0-9]+$"),
+        _ => None,
+    }
+}
+
+/// Quote a string as a YAML scalar. JSON strings are valid YAML.
+fn yaml_str(s: &str) -> String {
+    Value::from(s).to_string()
+}
+
+/// The description of an item, followed by the values it accepts, if any.
+fn description(item: &EnvItemInfo) -> String {
+    if item.values.is_empty() {
+        return item.description.to_owned();
+    }
+    let values = item
+        .values
+        .iter()
+        .map(|value| format!("`{value}`"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!("{} One of: {values}.", item.description)
+}
+
+/// Render sets of conditions, any of which may hold, e.g.
+/// `` `SIGNER_TYPE=local` or `SIGNER_TYPE=aws` ``.
+fn conditions(sets: &[&[EnvCondition]]) -> String {
+    sets.iter()
+        .map(|set| {
+            set.iter()
+                .map(|condition| format!("`{condition}`"))
+                .collect::<Vec<_>>()
+                .join(" and ")
+        })
+        .collect::<Vec<_>>()
+        .join(" or ")
+}
+
+/// Escape a value for use in a Markdown table cell.
+fn escape_cell(s: &str) -> String {
+    s.replace('|', "\\|").replace('\n', " ")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::utils::{
+        calc::SlotCalculator,
+        from_env::{EnvSource, FromEnvErr},
+    };
+    use signet_constants::SignetSystemConstants;
+
+    #[derive(Debug)]
+    struct Both;
+
+    impl FromEnv for Both {
+        fn inventory() -> Vec<&'static EnvItemInfo> {
+            let mut items = SlotCalculator::inventory();
+            items.extend(SignetSystemConstants::inventory());
+            items
+        }
+
+        fn from_source_with_prefix(
+            _source: &dyn EnvSource,
+            _prefix: &str,
+        ) -> Result<Self, FromEnvErr> {
+            Ok(Both)
+        }
+    }
+
+    #[test]
+    fn dedup_and_group() {
+        let docs = EnvDocs::of::<Both>();
+        assert_eq!(docs.groups().len(), 1);
+        assert_eq!(docs.groups()[0].name, "SlotCalculator");
+        assert_eq!(docs.vars().count(), SlotCalculator::inventory().len());
+
+        // Optional in the `SlotCalculator`, but required by the
+        // `SignetSystemConstants`.
+        let chain_name: Vec<_> = docs
+            .vars()
+            .filter(|var| var.item.var == "CHAIN_NAME")
+            .collect();
+        assert_eq!(chain_name.len(), 1);
+        let chain_name = chain_name[0];
+        assert!(!chain_name.optional);
+        assert!(chain_name.conditions.is_empty());
+        assert!(docs
+            .to_markdown()
+            .contains("| `CHAIN_NAME` | `KnownChains` | yes |"));
+    }
+
+    #[cfg(feature = "aws")]
+    #[test]
+    fn merge_enum_variants() {
+        let docs = EnvDocs::of::<crate::utils::signer::SignerConfig>();
+        let var = |name| docs.vars().find(|var| var.item.var == name).unwrap();
+
+        let key = var("SIGNER_KEY");
+        assert!(!key.optional);
+        assert!(key.secret);
+        let values: Vec<_> = key.conditions.iter().map(|set| set[0].value).collect();
+        assert_eq!(values, ["local", "aws"]);
+        assert!(docs
+            .to_markdown()
+            .contains("| yes, if `SIGNER_TYPE=local` or `SIGNER_TYPE=aws` |"));
+
+        let chain_id = var("SIGNER_CHAIN_ID");
+        assert!(chain_id.optional);
+        assert_eq!(chain_id.conditions.len(), 1);
+
+        let json: serde_json::Value = serde_json::from_str(&docs.to_json()).unwrap();
+        let key = &json["groups"][0]["vars"][1];
+        assert_eq!(key["var"], "SIGNER_KEY");
+        assert_eq!(key["optional"], false);
+        assert_eq!(key["conditions"][1][0]["value"], "aws");
+    }
+
+    #[test]
+    fn renderers() {
+        let docs = EnvDocs::of::<crate::utils::otlp::OtelConfig>();
+
+        let markdown = docs.to_markdown();
+        assert!(markdown.starts_with("## OtelConfig\n\n| Variable |"));
+        assert!(markdown.contains(
+            "| `OTEL_ENVIRONMENT_NAME` | `String` | no | `unknown` | OTLP environment name"
+        ));
+
+        let example = docs.to_env_example();
+        assert!(example.contains("# Type: String, optional\n#OTEL_ENVIRONMENT_NAME=unknown\n"));
+
+        let json: serde_json::Value = serde_json::from_str(&docs.to_json()).unwrap();
+        assert_eq!(json["groups"][0]["name"], "OtelConfig");
+        assert_eq!(json["groups"][0]["vars"][2]["default"], "unknown");
+    }
+}
@@ -0,0 +1,222 @@
+use super::{source::file_var, EnvItemInfo, EnvSource};
+use core::fmt::{self, Debug, Display, Formatter};
+use serde::Serialize;
+
+/// The placeholder shown in place of the raw value of secret variables.
+const REDACTED: &str = "[REDACTED]";
+
+/// Where the value of a variable in an [`EffectiveConfig`] came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValueSource {
+    /// The variable is set in the source.
+    Env,
+    /// The variable was read from the file at the path in its `_FILE`
+    /// variable. See [`FileIndirect`].
+    ///
+    /// [`FileIndirect`]: super::FileIndirect
+    File,
+    /// The variable is unset, and its deprecated alias is set.
+    Alias(&'static str),
+    /// The variable is unset or empty, and its default was used.
+    Default,
+    /// The value was determined by a preset selected by another variable,
+    /// e.g. the `CHAIN_NAME` of a [`SlotCalculator`].
+    ///
+    /// [`SlotCalculator`]: crate::utils::calc::SlotCalculator
+    Preset(&'static str),
+    /// The variable is unset, and has no default.
+    Unset,
+}
+
+impl Display for ValueSource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Env => f.write_str("env"),
+            Self::File => f.write_str("file"),
+            Self::Alias(alias) => write!(f, "alias {alias}"),
+            Self::Default => f.write_str("default"),
+            Self::Preset(var) => write!(f, "preset {var}"),
+            Self::Unset => f.write_str("unset"),
+        }
+    }
+}
+
+/// The value a variable took in an [`EffectiveConfig`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EffectiveValue {
+    /// The environment variable name, including any prefix.
+    pub var: &'static str,
+    /// The raw string value, if any. This is redacted for secret variables,
+    /// and is the path for variables read from a file.
+    pub raw: Option<String>,
+    /// The [`Debug`] representation of the parsed value, if known. This is
+    /// redacted for secret variables.
+    pub parsed: Option<String>,
+    /// Where the value came from.
+    pub source: ValueSource,
+    /// Whether the value is a secret. See [`EnvItemInfo::is_secret`].
+    pub secret: bool,
+}
+
+impl EffectiveValue {
+    /// Determine the raw value and source of the `item` in the `source`. The
+    /// parsed value is not known, see [`EffectiveValue::with_parsed`].
+    pub fn resolve(item: &'static EnvItemInfo, source: &dyn EnvSource) -> Self {
+        let set = |var: &str| source.get(var).ok().filter(|value| !value.is_empty());
+
+        let (raw, value_source) = if let Some(raw) = set(item.var) {
+            (Some(raw), ValueSource::Env)
+        } else if let Some(path) = item.file.then(|| set(&file_var(item.var))).flatten() {
+            return Self {
+                var: item.var,
+                raw: Some(path),
+                parsed: None,
+                source: ValueSource::File,
+                secret: item.is_secret(),
+            };
+        } else if let Some((alias, raw)) = item
+            .aliases
+            .iter()
+            .find_map(|alias| set(alias).map(|raw| (*alias, raw)))
+        {
+            (Some(raw), ValueSource::Alias(alias))
+        } else if let Some(default) = item.default {
+            (Some(default.to_owned()), ValueSource::Default)
+        } else {
+            (source.get(item.var).ok(), ValueSource::Unset)
+        };
+
+        let secret = item.is_secret();
+        Self {
+            var: item.var,
+            raw: raw.map(|raw| if secret { REDACTED.to_owned() } else { raw }),
+            parsed: None,
+            source: value_source,
+            secret,
+        }
+    }
+
+    /// Find the item for `var` in the `items`, and resolve it. See
+    /// [`EffectiveValue::resolve`].
+    pub fn find(items: &[&'static EnvItemInfo], var: &str, source: &dyn EnvSource) -> Option<Self> {
+        items
+            .iter()
+            .find(|item| item.var == var)
+            .map(|item| Self::resolve(item, source))
+    }
+
+    /// Set the parsed value, from its [`Debug`] representation. This is
+    /// redacted if the value is a secret.
+    pub fn with_parsed(mut self, parsed: &dyn Debug) -> Self {
+        self.parsed = Some(if self.secret {
+            REDACTED.to_owned()
+        } else {
+            format!("{parsed:?}")
+        });
+        self
+    }
+
+    /// Set the source, e.g. to [`ValueSource::Preset`].
+    pub const fn with_source(mut self, source: ValueSource) -> Self {
+        self.source = source;
+        self
+    }
+}
+
+/// The configuration a [`FromEnv`] type was loaded with, as a tree that
+/// mirrors the type, produced by [`FromEnv::effective_config`].
+///
+/// Each variable records its raw value, its parsed value and where it came
+/// from, e.g. the environment or a default. Secrets are redacted, so the tree
+/// may be logged, as [`init`] does if `TRACING_LOG_CONFIG` is set.
+///
+/// ```
+/// # use init4_bin_base::utils::{calc::SlotCalculator, from_env::{FromEnv, ValueSource}};
+/// # use std::collections::HashMap;
+/// let source = HashMap::from([("CHAIN_NAME".to_owned(), "mainnet".to_owned())]);
+/// let calc = SlotCalculator::from_source(&source)?;
+///
+/// let config = calc.effective_config(&source);
+/// let duration = config.find("SLOT_DURATION").unwrap();
+/// assert_eq!(duration.parsed.as_deref(), Some("12s"));
+/// assert_eq!(duration.source, ValueSource::Preset("CHAIN_NAME"));
+/// # Ok::<_, init4_bin_base::utils::from_env::FromEnvErr>(())
+/// ```
+///
+/// [`FromEnv`]: super::FromEnv
+/// [`FromEnv::effective_config`]: super::FromEnv::effective_config
+/// [`init`]: crate::init
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct EffectiveConfig {
+    /// The name of the config type.
+    pub name: &'static str,
+    /// The key of the config in its parent, empty for the root.
+    pub key: String,
+    /// The variables read directly by the config.
+    pub values: Vec<EffectiveValue>,
+    /// The nested configs.
+    pub children: Vec<EffectiveConfig>,
+}
+
+impl EffectiveConfig {
+    /// Create an empty config tree.
+    pub const fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            key: String::new(),
+            values: Vec::new(),
+            children: Vec::new(),
+        }
+    }
+
+    /// Create a config tree with the items of an inventory, whose parsed
+    /// values are not known. This is the default for hand-written
+    /// [`FromEnv`] impls.
+    ///
+    /// [`FromEnv`]: super::FromEnv
+    pub fn from_inventory(
+        name: &'static str,
+        items: &[&'static EnvItemInfo],
+        source: &dyn EnvSource,
+    ) -> Self {
+        let mut config = Self::new(name);
+        config.values = items
+            .iter()
+            .filter(|item| item.is_active(source))
+            .map(|item| EffectiveValue::resolve(item, source))
+            .collect();
+        config
+    }
+
+    /// Add a variable.
+    pub fn push_value(&mut self, value: EffectiveValue) {
+        self.values.push(value);
+    }
+
+    /// Add a nested config, at the given `key`.
+    pub fn push_child(&mut self, key: impl Into<String>, mut child: Self) {
+        child.key = key.into();
+        self.children.push(child);
+    }
+
+    /// Iterate over the variables of the config and its nested configs,
+    /// depth first.
+    pub fn iter(&self) -> Box<dyn Iterator<Item = &EffectiveValue> + '_> {
+        Box::new(
+            self.values
+                .iter()
+                .chain(self.children.iter().flat_map(Self::iter)),
+        )
+    }
+
+    /// Find the variable with the given name.
+    pub fn find(&self, var: &str) -> Option<&EffectiveValue> {
+        self.iter().find(|value| value.var == var)
+    }
+
+    /// Render the tree as compact JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("serializing to a string cannot fail")
+    }
+}
@@ -0,0 +1,78 @@
+use super::{EnvSource, FromEnvErr, FromEnvVar};
+use core::fmt;
+use zeroize::Zeroize;
+
+/// A sensitive config value, such as a private key or an API secret.
+///
+/// The value is redacted in the [`Debug`] and [`Display`] output, so that
+/// configs holding secrets can be logged safely, and is zeroized when
+/// dropped. It can only be accessed via [`Secret::expose`].
+///
+/// ```
+/// # use init4_bin_base::utils::from_env::Secret;
+/// let secret = Secret::new(String::from("hunter2"));
+/// assert_eq!(format!("{secret:?}"), "Secret([REDACTED])");
+/// assert_eq!(secret.to_string(), "[REDACTED]");
+/// assert_eq!(secret.expose(), "hunter2");
+/// ```
+///
+/// [`Display`]: fmt::Display
+#[derive(Clone, Default)]
+pub struct Secret<T: Zeroize>(T);
+
+impl<T: Zeroize> Secret<T> {
+    /// Wrap a sensitive value.
+    pub const fn new(value: T) -> Self {
+        Self(value)
+    }
+
+    /// Get a reference to the sensitive value. Take care not to log or
+    /// otherwise leak it.
+    pub const fn expose(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: Zeroize> From<T> for Secret<T> {
+    fn from(value: T) -> Self {
+        Self(value)
+    }
+}
+
+impl<T: Zeroize> fmt::Debug for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Secret([REDACTED])")
+    }
+}
+
+impl<T: Zeroize> fmt::Display for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("[REDACTED]")
+    }
+}
+
+impl<T: Zeroize> Drop for Secret<T> {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl<T> FromEnvVar for Secret<T>
+where
+    T: FromEnvVar + Zeroize,
+{
+    const VALUES: &'static [&'static str] = T::VALUES;
+    const SECRET: bool = true;
+
+    fn from_env_var_in(source: &dyn EnvSource, env_var: &str) -> Result<Self, FromEnvErr> {
+        T::from_env_var_in(source, env_var).map(Self)
+    }
+
+    fn from_env_var_in_with_separator(
+        source: &dyn EnvSource,
+        env_var: &str,
+        separator: &str,
+    ) -> Result<Self, FromEnvErr> {
+        T::from_env_var_in_with_separator(source, env_var, separator).map(Self)
+    }
+}
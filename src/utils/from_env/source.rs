@@ -0,0 +1,584 @@
+use super::FromEnvErr;
+use core::fmt;
+use std::{
+    collections::{BTreeMap, HashMap},
+    env::VarError,
+    hash::BuildHasher,
+    path::Path,
+    sync::Arc,
+};
+use zeroize::Zeroizing;
+
+/// A source of configuration values, keyed by environment variable name.
+///
+/// [`FromEnv`] and [`FromEnvVar`] implementations read all values through an
+/// [`EnvSource`], which allows loading configuration from places other than
+/// the process environment. The crate provides the following sources:
+///
+/// - [`ProcessEnv`]: the process environment, via [`std::env::var`].
+/// - [`HashMap`] and [`BTreeMap`]: in-memory values, useful for tests.
+/// - [`DotEnvFile`]: values parsed from a `.env` file.
+/// - [`LayeredSource`]: several sources, resolved in precedence order.
+///
+/// [`FileIndirect`] and [`Aliased`] wrap another source to resolve a single
+/// variable.
+///
+/// [`FromEnv`]: super::FromEnv
+/// [`FromEnvVar`]: super::FromEnvVar
+pub trait EnvSource: fmt::Debug + Send + Sync {
+    /// Get the value of the variable. This has the same semantics as
+    /// [`std::env::var`], i.e. it returns [`VarError::NotPresent`] if the
+    /// variable is not set.
+    fn get(&self, var: &str) -> Result<String, VarError>;
+
+    /// The names of the variables set in the source, if it can list them.
+    /// This is used to detect unknown variables, e.g. typos, when checking a
+    /// config. The default implementation lists none.
+    fn vars(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// True if the source is the [`ProcessEnv`]. This allows [`FromEnv`]
+    /// impls that can only load from the process environment to reject other
+    /// sources.
+    ///
+    /// [`FromEnv`]: super::FromEnv
+    fn is_process_env(&self) -> bool {
+        false
+    }
+}
+
+impl<S: EnvSource + ?Sized> EnvSource for &S {
+    fn get(&self, var: &str) -> Result<String, VarError> {
+        (**self).get(var)
+    }
+
+    fn vars(&self) -> Vec<String> {
+        (**self).vars()
+    }
+
+    fn is_process_env(&self) -> bool {
+        (**self).is_process_env()
+    }
+}
+
+impl<S: EnvSource + ?Sized> EnvSource for Box<S> {
+    fn get(&self, var: &str) -> Result<String, VarError> {
+        (**self).get(var)
+    }
+
+    fn vars(&self) -> Vec<String> {
+        (**self).vars()
+    }
+
+    fn is_process_env(&self) -> bool {
+        (**self).is_process_env()
+    }
+}
+
+impl<S: EnvSource + ?Sized> EnvSource for Arc<S> {
+    fn get(&self, var: &str) -> Result<String, VarError> {
+        (**self).get(var)
+    }
+
+    fn vars(&self) -> Vec<String> {
+        (**self).vars()
+    }
+
+    fn is_process_env(&self) -> bool {
+        (**self).is_process_env()
+    }
+}
+
+/// The process environment. This is the source used by [`FromEnv::from_env`]
+/// and [`FromEnvVar::from_env_var`].
+///
+/// [`FromEnv::from_env`]: super::FromEnv::from_env
+/// [`FromEnvVar::from_env_var`]: super::FromEnvVar::from_env_var
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ProcessEnv;
+
+impl EnvSource for ProcessEnv {
+    fn get(&self, var: &str) -> Result<String, VarError> {
+        std::env::var(var)
+    }
+
+    fn vars(&self) -> Vec<String> {
+        std::env::vars_os()
+            .filter_map(|(var, _)| var.into_string().ok())
+            .collect()
+    }
+
+    fn is_process_env(&self) -> bool {
+        true
+    }
+}
+
+impl<S> EnvSource for HashMap<String, String, S>
+where
+    S: BuildHasher + Send + Sync,
+{
+    fn get(&self, var: &str) -> Result<String, VarError> {
+        HashMap::get(self, var).cloned().ok_or(VarError::NotPresent)
+    }
+
+    fn vars(&self) -> Vec<String> {
+        self.keys().cloned().collect()
+    }
+}
+
+impl EnvSource for BTreeMap<String, String> {
+    fn get(&self, var: &str) -> Result<String, VarError> {
+        BTreeMap::get(self, var)
+            .cloned()
+            .ok_or(VarError::NotPresent)
+    }
+
+    fn vars(&self) -> Vec<String> {
+        self.keys().cloned().collect()
+    }
+}
+
+/// Errors that can occur when loading a [`DotEnvFile`].
+#[derive(Debug, thiserror::Error)]
+pub enum DotEnvError {
+    /// The file could not be read.
+    #[error("failed to read env file: {0}")]
+    Io(#[from] std::io::Error),
+    /// A line of the file could not be parsed.
+    #[error("invalid env file at line {line}: {reason}")]
+    Parse {
+        /// The 1-indexed line number.
+        line: usize,
+        /// Why the line is invalid.
+        reason: &'static str,
+    },
+}
+
+/// Values parsed from a `.env` file.
+///
+/// The file format is the common subset supported by docker and most dotenv
+/// implementations:
+///
+/// - One `KEY=VALUE` assignment per line, optionally preceded by `export`.
+/// - Blank lines and lines starting with `#` are ignored.
+/// - Unquoted values are trimmed, and may be followed by a ` #` comment.
+/// - Single-quoted values are taken literally.
+/// - Double-quoted values support the `\n`, `\t`, `\\` and `\"` escapes.
+/// - Later assignments to the same key override earlier ones.
+///
+/// ```
+/// # use init4_bin_base::utils::from_env::{DotEnvFile, EnvSource};
+/// let file = DotEnvFile::parse("# comment\nexport CHAIN_NAME=parmigiana\nOTEL_LEVEL='debug' # why not\n").unwrap();
+/// assert_eq!(file.get("CHAIN_NAME").unwrap(), "parmigiana");
+/// assert_eq!(file.get("OTEL_LEVEL").unwrap(), "debug");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DotEnvFile {
+    vars: HashMap<String, String>,
+}
+
+impl DotEnvFile {
+    /// Read and parse the file at the given path.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, DotEnvError> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// Parse the contents of a `.env` file.
+    pub fn parse(contents: &str) -> Result<Self, DotEnvError> {
+        let mut vars = HashMap::new();
+        for (idx, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = parse_line(line).map_err(|reason| DotEnvError::Parse {
+                line: idx + 1,
+                reason,
+            })?;
+            vars.insert(key.to_owned(), value);
+        }
+        Ok(Self { vars })
+    }
+
+    /// Iterate over the parsed variables.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.vars.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+}
+
+impl EnvSource for DotEnvFile {
+    fn get(&self, var: &str) -> Result<String, VarError> {
+        self.vars.get(var).cloned().ok_or(VarError::NotPresent)
+    }
+
+    fn vars(&self) -> Vec<String> {
+        self.vars.keys().cloned().collect()
+    }
+}
+
+/// Parse a single non-empty, non-comment line of a `.env` file.
+fn parse_line(line: &str) -> Result<(&str, String), &'static str> {
+    let line = line
+        .strip_prefix("export")
+        .filter(|rest| rest.starts_with(char::is_whitespace))
+        .map_or(line, str::trim_start);
+
+    let (key, value) = line.split_once('=').ok_or("expected KEY=VALUE")?;
+    let key = key.trim_end();
+    if key.is_empty() || key.contains(char::is_whitespace) {
+        return Err("invalid key");
+    }
+
+    let value = value.trim_start();
+    let (value, rest) = if let Some(quoted) = value.strip_prefix('\'') {
+        let end = quoted.find('\'').ok_or("unterminated single quote")?;
+        (quoted[..end].to_owned(), &quoted[end + 1..])
+    } else if let Some(quoted) = value.strip_prefix('"') {
+        parse_double_quoted(quoted)?
+    } else {
+        let end = value.find(" #").unwrap_or(value.len());
+        (value[..end].trim_end().to_owned(), "")
+    };
+
+    let rest = rest.trim_start();
+    if !rest.is_empty() && !rest.starts_with('#') {
+        return Err("unexpected characters after quoted value");
+    }
+
+    Ok((key, value))
+}
+
+/// Parse a double-quoted value, starting after the opening quote. Returns the
+/// unescaped value and the remainder of the line after the closing quote.
+fn parse_double_quoted(s: &str) -> Result<(String, &str), &'static str> {
+    let mut value = String::new();
+    let mut chars = s.char_indices();
+    while let Some((idx, c)) = chars.next() {
+        match c {
+            '"' => return Ok((value, &s[idx + 1..])),
+            '\\' => match chars.next().map(|(_, c)| c) {
+                Some('n') => value.push('\n'),
+                Some('t') => value.push('\t'),
+                Some(c @ ('\\' | '"')) => value.push(c),
+                Some(c) => {
+                    value.push('\\');
+                    value.push(c);
+                }
+                None => break,
+            },
+            c => value.push(c),
+        }
+    }
+    Err("unterminated double quote")
+}
+
+/// Several [`EnvSource`]s, resolved in precedence order. The value of a
+/// variable is taken from the first layer in which it is present.
+///
+/// ```
+/// # use init4_bin_base::utils::from_env::{DotEnvFile, EnvSource, LayeredSource, ProcessEnv};
+/// # fn f() -> Result<(), Box<dyn std::error::Error>> {
+/// // Values set in the process environment override those in the file.
+/// let source = LayeredSource::new()
+///     .with_layer(ProcessEnv)
+///     .with_layer(DotEnvFile::load(".env")?);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Default)]
+pub struct LayeredSource {
+    layers: Vec<Box<dyn EnvSource>>,
+}
+
+impl LayeredSource {
+    /// Create a new source with no layers.
+    pub const fn new() -> Self {
+        Self { layers: Vec::new() }
+    }
+
+    /// Add a layer, with lower precedence than all existing layers.
+    pub fn with_layer(mut self, layer: impl EnvSource + 'static) -> Self {
+        self.push(layer);
+        self
+    }
+
+    /// Add a layer, with lower precedence than all existing layers.
+    pub fn push(&mut self, layer: impl EnvSource + 'static) {
+        self.layers.push(Box::new(layer));
+    }
+}
+
+impl EnvSource for LayeredSource {
+    fn get(&self, var: &str) -> Result<String, VarError> {
+        for layer in &self.layers {
+            match layer.get(var) {
+                Err(VarError::NotPresent) => continue,
+                res => return res,
+            }
+        }
+        Err(VarError::NotPresent)
+    }
+
+    fn vars(&self) -> Vec<String> {
+        let mut vars: Vec<_> = self.layers.iter().flat_map(|layer| layer.vars()).collect();
+        vars.sort_unstable();
+        vars.dedup();
+        vars
+    }
+}
+
+/// An [`EnvSource`] that returns the same value for every variable. This is
+/// used to parse default values and the elements of lists via
+/// [`FromEnvVar::from_env_var_in`].
+///
+/// [`FromEnvVar::from_env_var_in`]: super::FromEnvVar::from_env_var_in
+#[derive(Debug, Clone, Copy)]
+pub struct SingleValue<'a>(pub &'a str);
+
+impl EnvSource for SingleValue<'_> {
+    fn get(&self, _var: &str) -> Result<String, VarError> {
+        Ok(self.0.to_owned())
+    }
+}
+
+/// The name of the variable holding the path of the file that may provide
+/// the value of `var`, i.e. `{var}_FILE`.
+pub(super) fn file_var(var: &str) -> String {
+    format!("{var}_FILE")
+}
+
+/// An [`EnvSource`] that resolves docker and kubernetes style `_FILE`
+/// indirection for a single variable.
+///
+/// If `{var}_FILE` is set in the underlying source, the value of `var` is
+/// the trimmed contents of the file at that path. Otherwise the value of
+/// `var` is read from the underlying source as usual. It is an error for
+/// both `var` and `{var}_FILE` to be set.
+///
+/// ```no_run
+/// # use init4_bin_base::utils::from_env::{EnvSource, FileIndirect, ProcessEnv};
+/// # fn f() -> Result<(), Box<dyn std::error::Error>> {
+/// // With `SIGNER_KEY_FILE=/run/secrets/signer_key`
+/// let source = FileIndirect::resolve(&ProcessEnv, "SIGNER_KEY")?;
+/// let key = source.get("SIGNER_KEY")?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct FileIndirect<'a> {
+    source: &'a dyn EnvSource,
+    file: Option<(String, Zeroizing<String>)>,
+}
+
+impl<'a> FileIndirect<'a> {
+    /// Resolve the `{var}_FILE` indirection for `var`, reading the file if
+    /// necessary.
+    pub fn resolve(source: &'a dyn EnvSource, var: &str) -> Result<Self, FromEnvErr> {
+        let is_set = |var: &str| source.get(var).is_ok_and(|s| !s.is_empty());
+
+        let path = match source.get(&file_var(var)) {
+            Ok(path) if !path.is_empty() => path,
+            _ => return Ok(Self { source, file: None }),
+        };
+        if is_set(var) {
+            return Err(FromEnvErr::Conflict(var.to_owned()));
+        }
+
+        let contents = std::fs::read_to_string(&path)
+            .map(Zeroizing::new)
+            .map_err(|err| FromEnvErr::FileError(var.to_owned(), path, err))?;
+        let contents = Zeroizing::new(contents.trim().to_owned());
+
+        Ok(Self {
+            source,
+            file: Some((var.to_owned(), contents)),
+        })
+    }
+}
+
+impl fmt::Debug for FileIndirect<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // File contents are typically secrets, so only the var is shown.
+        f.debug_struct("FileIndirect")
+            .field("source", &self.source)
+            .field("file", &self.file.as_ref().map(|(var, _)| var))
+            .finish()
+    }
+}
+
+impl EnvSource for FileIndirect<'_> {
+    fn get(&self, var: &str) -> Result<String, VarError> {
+        match &self.file {
+            Some((file_var, contents)) if file_var == var => Ok(contents.to_string()),
+            _ => self.source.get(var),
+        }
+    }
+}
+
+/// An [`EnvSource`] that falls back to deprecated aliases for a single
+/// variable.
+///
+/// If `var` is not set in the underlying source, the first alias that is set
+/// is read in its place, and a warning naming the replacement is logged.
+/// All other variables are read from the underlying source as usual.
+///
+/// ```
+/// # use init4_bin_base::utils::from_env::{Aliased, EnvSource};
+/// # use std::collections::HashMap;
+/// let source = HashMap::from([("OLD_NAME".to_owned(), "value".to_owned())]);
+/// let aliased = Aliased::new(&source, "NEW_NAME", ["OLD_NAME"]);
+/// assert_eq!(aliased.get("NEW_NAME").unwrap(), "value");
+/// ```
+#[derive(Debug)]
+pub struct Aliased<'a> {
+    source: &'a dyn EnvSource,
+    var: String,
+    alias: Option<String>,
+}
+
+impl<'a> Aliased<'a> {
+    /// Resolve `var` to the first of its `aliases` that is set, if `var` is
+    /// not set itself.
+    pub fn new<I>(source: &'a dyn EnvSource, var: impl Into<String>, aliases: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        let var = var.into();
+        let alias = if source.get(&var).is_ok() {
+            None
+        } else {
+            aliases
+                .into_iter()
+                .map(Into::into)
+                .find(|alias| source.get(alias).is_ok())
+        };
+
+        if let Some(alias) = &alias {
+            tracing::warn!(%alias, replacement = %var, "environment variable {alias} is deprecated, use {var} instead");
+        }
+
+        Self { source, var, alias }
+    }
+
+    /// Get the alias that is read in place of the variable, if any.
+    pub fn alias(&self) -> Option<&str> {
+        self.alias.as_deref()
+    }
+}
+
+impl EnvSource for Aliased<'_> {
+    fn get(&self, var: &str) -> Result<String, VarError> {
+        match &self.alias {
+            Some(alias) if self.var == var => self.source.get(alias),
+            _ => self.source.get(var),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn dotenv_parsing() {
+        let file = DotEnvFile::parse(
+            r#"
+            # A comment
+            PLAIN=value
+            export EXPORTED = spaced value   # trailing comment
+            SINGLE='literal \n # not a comment'
+            DOUBLE="line\nbreak \"quoted\"" # comment
+            EMPTY=
+            HASH=a#b
+            PLAIN=overridden
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(file.get("PLAIN").unwrap(), "overridden");
+        assert_eq!(file.get("EXPORTED").unwrap(), "spaced value");
+        assert_eq!(file.get("SINGLE").unwrap(), "literal \\n # not a comment");
+        assert_eq!(file.get("DOUBLE").unwrap(), "line\nbreak \"quoted\"");
+        assert_eq!(file.get("EMPTY").unwrap(), "");
+        assert_eq!(file.get("HASH").unwrap(), "a#b");
+        assert_eq!(file.get("MISSING"), Err(VarError::NotPresent));
+    }
+
+    #[test]
+    fn dotenv_errors() {
+        for (contents, line) in [
+            ("OK=1\nNOT_AN_ASSIGNMENT", 2),
+            ("BAD KEY=1", 1),
+            ("A=1\n\nQUOTE=\"unterminated", 3),
+            ("QUOTE='a' b", 1),
+        ] {
+            let err = DotEnvFile::parse(contents).unwrap_err();
+            assert!(matches!(err, DotEnvError::Parse { line: l, .. } if l == line));
+        }
+    }
+
+    #[test]
+    fn layered_precedence() {
+        let high = HashMap::from([("A".to_owned(), "high".to_owned())]);
+        let low = BTreeMap::from([
+            ("A".to_owned(), "low".to_owned()),
+            ("B".to_owned(), "low".to_owned()),
+        ]);
+        let source = LayeredSource::new().with_layer(high).with_layer(low);
+
+        assert_eq!(source.get("A").unwrap(), "high");
+        assert_eq!(source.get("B").unwrap(), "low");
+        assert_eq!(source.get("C"), Err(VarError::NotPresent));
+    }
+
+    #[test]
+    fn file_indirection() {
+        let path = std::env::temp_dir().join("init4-file-indirection-test");
+        std::fs::write(&path, "  from a file\n").unwrap();
+        let path = path.to_str().unwrap().to_owned();
+
+        let source = HashMap::from([
+            ("PLAIN".to_owned(), "plain".to_owned()),
+            ("SECRET_FILE".to_owned(), path.clone()),
+            ("BOTH".to_owned(), "value".to_owned()),
+            ("BOTH_FILE".to_owned(), path),
+            ("MISSING_FILE".to_owned(), "/does/not/exist".to_owned()),
+        ]);
+
+        let resolved = FileIndirect::resolve(&source, "PLAIN").unwrap();
+        assert_eq!(resolved.get("PLAIN").unwrap(), "plain");
+
+        let resolved = FileIndirect::resolve(&source, "SECRET").unwrap();
+        assert_eq!(resolved.get("SECRET").unwrap(), "from a file");
+        assert_eq!(resolved.get("PLAIN").unwrap(), "plain");
+        assert!(!format!("{resolved:?}").contains("from a file"));
+
+        let err = FileIndirect::resolve(&source, "BOTH").unwrap_err();
+        assert!(matches!(err, FromEnvErr::Conflict(ref var) if var == "BOTH"));
+
+        let err = FileIndirect::resolve(&source, "MISSING").unwrap_err();
+        assert!(matches!(err, FromEnvErr::FileError(ref var, ..) if var == "MISSING"));
+    }
+
+    #[test]
+    fn alias_fallback() {
+        let source = HashMap::from([
+            ("NEW".to_owned(), "new".to_owned()),
+            ("OLD".to_owned(), "old".to_owned()),
+            ("OLDER".to_owned(), "older".to_owned()),
+        ]);
+
+        let aliased = Aliased::new(&source, "NEW", ["OLD"]);
+        assert_eq!(aliased.alias(), None);
+        assert_eq!(aliased.get("NEW").unwrap(), "new");
+
+        let aliased = Aliased::new(&source, "RENAMED", ["MISSING", "OLDER", "OLD"]);
+        assert_eq!(aliased.alias(), Some("OLDER"));
+        assert_eq!(aliased.get("RENAMED").unwrap(), "older");
+        assert_eq!(aliased.get("OLD").unwrap(), "old");
+
+        let aliased = Aliased::new(&source, "RENAMED", ["MISSING"]);
+        assert_eq!(aliased.get("RENAMED"), Err(VarError::NotPresent));
+    }
+}
@@ -0,0 +1,335 @@
+use super::{parse_source_if_present, EnvSource, FromEnvErr, FromEnvVar};
+use core::{fmt, ops::Deref, str::FromStr, time::Duration};
+
+const NANOS_PER_SEC: u128 = 1_000_000_000;
+
+/// Duration units, with their length in nanoseconds.
+const DURATION_UNITS: &[(&str, u128)] = &[
+    ("ns", 1),
+    ("us", 1_000),
+    ("µs", 1_000),
+    ("ms", 1_000_000),
+    ("s", NANOS_PER_SEC),
+    ("m", 60 * NANOS_PER_SEC),
+    ("h", 60 * 60 * NANOS_PER_SEC),
+    ("d", 24 * 60 * 60 * NANOS_PER_SEC),
+];
+
+/// Byte size units, with their size in bytes.
+const BYTE_UNITS: &[(&str, u64)] = &[
+    ("b", 1),
+    ("kb", 1_000),
+    ("mb", 1_000_000),
+    ("gb", 1_000_000_000),
+    ("tb", 1_000_000_000_000),
+    ("kib", 1 << 10),
+    ("mib", 1 << 20),
+    ("gib", 1 << 30),
+    ("tib", 1 << 40),
+];
+
+/// Errors parsing a [`Duration`] or a [`ByteSize`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum UnitParseError {
+    /// The value is not a number followed by a unit.
+    #[error("invalid value `{0}`, expected a number followed by a unit, e.g. `{1}`")]
+    Invalid(String, &'static str),
+    /// The unit is not recognized.
+    #[error("unknown unit `{unit}`, expected one of: {}", .units.join(", "))]
+    UnknownUnit {
+        /// The unit in the value.
+        unit: String,
+        /// The accepted units.
+        units: Vec<&'static str>,
+    },
+    /// The value is too large.
+    #[error("value `{0}` is too large")]
+    Overflow(String),
+}
+
+/// Split `s` into `(number, unit)` segments, e.g. `1h30m` into `(1, "h")`
+/// and `(30, "m")`.
+fn segments(s: &str) -> Option<Vec<(u64, &str)>> {
+    let mut out = Vec::new();
+    let mut rest = s.trim();
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let number = rest[..digits].parse().ok()?;
+        rest = rest[digits..].trim_start();
+        let letters = rest
+            .find(|c: char| c.is_ascii_digit() || c.is_whitespace())
+            .unwrap_or(rest.len());
+        out.push((number, &rest[..letters]));
+        rest = rest[letters..].trim_start();
+    }
+    Some(out)
+}
+
+/// Parse a duration such as `500ms`, `12s`, `5m`, `1h` or `1h30m`.
+///
+/// The accepted units are `ns`, `us` (or `µs`), `ms`, `s`, `m`, `h` and `d`.
+/// A bare number, without a unit, is a multiple of `bare`. This allows
+/// variables that were historically plain integers to keep their meaning.
+///
+/// ```
+/// # use init4_bin_base::utils::from_env::parse_duration;
+/// # use std::time::Duration;
+/// let secs = Duration::from_secs(1);
+/// assert_eq!(parse_duration("1h30m", secs).unwrap(), Duration::from_secs(5400));
+/// assert_eq!(parse_duration("500ms", secs).unwrap(), Duration::from_millis(500));
+/// assert_eq!(parse_duration("12", secs).unwrap(), Duration::from_secs(12));
+/// ```
+pub fn parse_duration(s: &str, bare: Duration) -> Result<Duration, UnitParseError> {
+    let invalid = || UnitParseError::Invalid(s.to_owned(), "12s");
+    let segments = segments(s).filter(|s| !s.is_empty()).ok_or_else(invalid)?;
+
+    let mut nanos: u128 = 0;
+    for (number, unit) in segments {
+        let unit_nanos = if unit.is_empty() {
+            if nanos != 0 {
+                return Err(invalid());
+            }
+            bare.as_nanos()
+        } else {
+            DURATION_UNITS
+                .iter()
+                .find(|(name, _)| *name == unit)
+                .map(|(_, nanos)| *nanos)
+                .ok_or_else(|| UnitParseError::UnknownUnit {
+                    unit: unit.to_owned(),
+                    units: DURATION_UNITS.iter().map(|(name, _)| *name).collect(),
+                })?
+        };
+        nanos = (number as u128)
+            .checked_mul(unit_nanos)
+            .and_then(|n| nanos.checked_add(n))
+            .ok_or_else(|| UnitParseError::Overflow(s.to_owned()))?;
+    }
+
+    let secs =
+        u64::try_from(nanos / NANOS_PER_SEC).map_err(|_| UnitParseError::Overflow(s.to_owned()))?;
+    Ok(Duration::new(secs, (nanos % NANOS_PER_SEC) as u32))
+}
+
+/// Durations are parsed by [`parse_duration`], e.g. `500ms` or `5m`. Bare
+/// numbers are milliseconds. Use [`Seconds`] for variables whose bare numbers
+/// are seconds.
+impl FromEnvVar for Duration {
+    fn from_env_var_in(source: &dyn EnvSource, env_var: &str) -> Result<Self, FromEnvErr> {
+        let s = source
+            .get(env_var)
+            .map_err(|e| FromEnvErr::env_err(env_var, e))?;
+        if s.is_empty() {
+            return Err(FromEnvErr::empty(env_var));
+        }
+        parse_duration(&s, Duration::from_millis(1))
+            .map_err(|error| FromEnvErr::parse_error(env_var, error))
+    }
+}
+
+/// A [`Duration`] whose bare numbers are seconds, rather than milliseconds.
+///
+/// This is for variables that were historically an integer number of
+/// seconds, and now accept unit-suffixed durations as well.
+///
+/// ```
+/// # use init4_bin_base::utils::from_env::{FromEnvVar, Seconds};
+/// # use std::{collections::HashMap, time::Duration};
+/// let source = HashMap::from([
+///     ("BARE".to_owned(), "30".to_owned()),
+///     ("UNIT".to_owned(), "500ms".to_owned()),
+/// ]);
+/// assert_eq!(*Seconds::from_env_var_in(&source, "BARE")?, Duration::from_secs(30));
+/// assert_eq!(*Seconds::from_env_var_in(&source, "UNIT")?, Duration::from_millis(500));
+/// # Ok::<_, init4_bin_base::utils::from_env::FromEnvErr>(())
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Seconds(pub Duration);
+
+impl Seconds {
+    /// Create a duration of `secs` seconds.
+    pub const fn from_secs(secs: u64) -> Self {
+        Self(Duration::from_secs(secs))
+    }
+}
+
+impl Deref for Seconds {
+    type Target = Duration;
+
+    fn deref(&self) -> &Duration {
+        &self.0
+    }
+}
+
+impl From<Duration> for Seconds {
+    fn from(duration: Duration) -> Self {
+        Self(duration)
+    }
+}
+
+impl From<Seconds> for Duration {
+    fn from(seconds: Seconds) -> Self {
+        seconds.0
+    }
+}
+
+impl FromStr for Seconds {
+    type Err = UnitParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_duration(s, Duration::from_secs(1)).map(Self)
+    }
+}
+
+impl FromEnvVar for Seconds {
+    fn from_env_var_in(source: &dyn EnvSource, env_var: &str) -> Result<Self, FromEnvErr> {
+        parse_source_if_present(source, env_var)
+    }
+}
+
+/// A size in bytes, parsed from a number with an optional unit, e.g. `512`,
+/// `64KB` or `10MiB`.
+///
+/// The decimal units `kB`, `MB`, `GB` and `TB`, and the binary units `KiB`,
+/// `MiB`, `GiB` and `TiB`, are accepted, case-insensitively. A bare number is
+/// a number of bytes.
+///
+/// ```
+/// # use init4_bin_base::utils::from_env::ByteSize;
+/// let size: ByteSize = "10MiB".parse().unwrap();
+/// assert_eq!(size.as_u64(), 10 * 1024 * 1024);
+/// assert_eq!(size.to_string(), "10MiB");
+/// assert_eq!("1kb".parse::<ByteSize>().unwrap(), ByteSize(1000));
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ByteSize(pub u64);
+
+impl ByteSize {
+    /// Get the number of bytes.
+    pub const fn as_u64(self) -> u64 {
+        self.0
+    }
+}
+
+impl From<u64> for ByteSize {
+    fn from(bytes: u64) -> Self {
+        Self(bytes)
+    }
+}
+
+impl From<ByteSize> for u64 {
+    fn from(size: ByteSize) -> Self {
+        size.0
+    }
+}
+
+/// Formats the size in the largest binary unit that divides it exactly.
+impl fmt::Display for ByteSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (name, size) = [
+            ("TiB", 1 << 40),
+            ("GiB", 1 << 30),
+            ("MiB", 1 << 20),
+            ("KiB", 1 << 10),
+        ]
+        .into_iter()
+        .find(|(_, size)| self.0 != 0 && self.0.is_multiple_of(*size))
+        .unwrap_or(("B", 1));
+        write!(f, "{}{name}", self.0 / size)
+    }
+}
+
+impl FromStr for ByteSize {
+    type Err = UnitParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || UnitParseError::Invalid(s.to_owned(), "10MiB");
+        let [(number, unit)] = segments(s).ok_or_else(invalid)?[..] else {
+            return Err(invalid());
+        };
+        let unit = unit.to_ascii_lowercase();
+        let multiplier = if unit.is_empty() {
+            1
+        } else {
+            BYTE_UNITS
+                .iter()
+                .find(|(name, _)| *name == unit)
+                .map(|(_, size)| *size)
+                .ok_or_else(|| UnitParseError::UnknownUnit {
+                    unit,
+                    units: BYTE_UNITS.iter().map(|(name, _)| *name).collect(),
+                })?
+        };
+        number
+            .checked_mul(multiplier)
+            .map(Self)
+            .ok_or_else(|| UnitParseError::Overflow(s.to_owned()))
+    }
+}
+
+impl FromEnvVar for ByteSize {
+    fn from_env_var_in(source: &dyn EnvSource, env_var: &str) -> Result<Self, FromEnvErr> {
+        parse_source_if_present(source, env_var)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn durations() {
+        let ms = Duration::from_millis(1);
+        let ok = |s| parse_duration(s, ms).unwrap();
+        assert_eq!(ok("500ms"), Duration::from_millis(500));
+        assert_eq!(ok("12s"), Duration::from_secs(12));
+        assert_eq!(ok("5m"), Duration::from_secs(300));
+        assert_eq!(ok("1h"), Duration::from_secs(3600));
+        assert_eq!(ok("1d 2h"), Duration::from_secs(93_600));
+        assert_eq!(ok("250us"), Duration::from_micros(250));
+        assert_eq!(ok(" 42 "), Duration::from_millis(42));
+        assert_eq!(ok("0"), Duration::ZERO);
+
+        assert!(matches!(
+            parse_duration("", ms),
+            Err(UnitParseError::Invalid(..))
+        ));
+        assert!(matches!(
+            parse_duration("1s30", ms),
+            Err(UnitParseError::Invalid(..))
+        ));
+        assert!(matches!(
+            parse_duration("ms", ms),
+            Err(UnitParseError::Invalid(..))
+        ));
+        assert_eq!(
+            parse_duration("3w", ms).unwrap_err().to_string(),
+            "unknown unit `w`, expected one of: ns, us, µs, ms, s, m, h, d"
+        );
+        assert!(matches!(
+            parse_duration(&format!("{}d", u64::MAX), ms),
+            Err(UnitParseError::Overflow(..))
+        ));
+    }
+
+    #[test]
+    fn byte_sizes() {
+        let ok = |s: &str| s.parse::<ByteSize>().unwrap().as_u64();
+        assert_eq!(ok("512"), 512);
+        assert_eq!(ok("512B"), 512);
+        assert_eq!(ok("64KB"), 64_000);
+        assert_eq!(ok("10MiB"), 10 << 20);
+        assert_eq!(ok("2 gib"), 2 << 30);
+
+        assert!("10 MiB 2".parse::<ByteSize>().is_err());
+        assert!("MiB".parse::<ByteSize>().is_err());
+        assert!("1PiB".parse::<ByteSize>().is_err());
+        assert!(format!("{}TiB", u64::MAX).parse::<ByteSize>().is_err());
+
+        assert_eq!(ByteSize(0).to_string(), "0B");
+        assert_eq!(ByteSize(1000).to_string(), "1000B");
+        assert_eq!(ByteSize(3 << 30).to_string(), "3GiB");
+    }
+}
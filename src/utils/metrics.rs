@@ -1,8 +1,10 @@
-use crate::utils::from_env::{FromEnv, FromEnvErr, FromEnvVar};
+use crate::utils::from_env::{
+    EffectiveConfig, EffectiveValue, FromEnv, FromEnvErr, FromEnvVar, ValueSource,
+};
 use core::net::SocketAddr;
 use metrics_exporter_prometheus::PrometheusBuilder;
 
-use super::from_env::EnvItemInfo;
+use super::from_env::{prefixed_var, EnvItemInfo, EnvSource};
 
 /// Metrics port env var
 const METRICS_PORT: &str = "METRICS_PORT";
@@ -43,19 +45,41 @@ impl From<u16> for MetricsConfig {
 
 impl FromEnv for MetricsConfig {
     fn inventory() -> Vec<&'static EnvItemInfo> {
-        vec![&EnvItemInfo {
-            var: METRICS_PORT,
-            description: "Port on which to serve metrics, u16, defaults to 9000",
-            optional: true,
-        }]
+        vec![
+            &const {
+                EnvItemInfo::new(METRICS_PORT, "Port on which to serve metrics")
+                    .optional()
+                    .default("9000")
+                    .type_name("u16")
+                    .group("MetricsConfig")
+                    .key("port")
+            },
+        ]
     }
 
-    fn from_env() -> Result<Self, FromEnvErr> {
-        match u16::from_env_var(METRICS_PORT).map(Self::from) {
+    fn from_source_with_prefix(source: &dyn EnvSource, prefix: &str) -> Result<Self, FromEnvErr> {
+        match u16::from_env_var_in(source, &prefixed_var(prefix, METRICS_PORT)).map(Self::from) {
             Ok(cfg) => Ok(cfg),
             Err(_) => Ok(Self::default()),
         }
     }
+
+    fn effective_config_with_prefix(
+        &self,
+        source: &dyn EnvSource,
+        prefix: &str,
+    ) -> EffectiveConfig {
+        let item = Self::inventory_with_prefix(prefix)[0];
+        let mut value = EffectiveValue::resolve(item, source).with_parsed(&self.port);
+        // Unparseable values are replaced by the default.
+        if u16::from_env_var_in(source, item.var).is_err() {
+            value = value.with_source(ValueSource::Default);
+        }
+
+        let mut config = EffectiveConfig::new("MetricsConfig");
+        config.push_value(value);
+        config
+    }
 }
 
 /// Initialize a [`metrics_exporter_prometheus`] exporter.
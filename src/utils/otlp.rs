@@ -1,4 +1,6 @@
-use crate::utils::from_env::{EnvItemInfo, FromEnv, FromEnvErr, FromEnvVar};
+use crate::utils::from_env::{
+    prefixed_var, EnvItemInfo, EnvSource, FromEnv, FromEnvErr, FromEnvVar,
+};
 use opentelemetry::{trace::TracerProvider, KeyValue};
 use opentelemetry_sdk::{trace::SdkTracerProvider, Resource};
 use opentelemetry_semantic_conventions::{
@@ -84,41 +86,50 @@ pub struct OtelConfig {
 impl FromEnv for OtelConfig {
     fn inventory() -> Vec<&'static EnvItemInfo> {
         vec![
-            &EnvItemInfo {
-                var: OTEL_ENDPOINT,
-                description:
+            &const {
+                EnvItemInfo::new(
+                    OTEL_ENDPOINT,
                     "OTLP endpoint to send traces to, a url. If missing, disables OTLP exporting.",
-                optional: true,
+                )
+                .optional()
+                .type_name("Url")
+                .group("OtelConfig")
+                .key("endpoint")
             },
-            &EnvItemInfo {
-                var: OTEL_LEVEL,
-                description: "OTLP level to export. Follows the RUST_LOG env filter format. e.g. `OTEL_LEVEL=warn,my_crate=info`. Defaults to the value of `RUST_LOG` if not present.",
-                optional: true,
+            &const {
+                EnvItemInfo::new(OTEL_LEVEL, "OTLP level to export. Follows the RUST_LOG env filter format. e.g. `OTEL_LEVEL=warn,my_crate=info`. Defaults to the value of `RUST_LOG` if not present.")
+                    .optional()
+                    .type_name("EnvFilter")
+                    .group("OtelConfig")
+                    .key("level")
             },
-            &EnvItemInfo {
-                var: OTEL_ENVIRONMENT,
-                description: "OTLP environment name, a string",
-                optional: true,
+            &const {
+                EnvItemInfo::new(OTEL_ENVIRONMENT, "OTLP environment name, a string")
+                    .optional()
+                    .default("unknown")
+                    .type_name("String")
+                    .group("OtelConfig")
+                    .key("environment")
             },
         ]
     }
 
-    fn from_env() -> Result<Self, FromEnvErr> {
+    fn from_source_with_prefix(source: &dyn EnvSource, prefix: &str) -> Result<Self, FromEnvErr> {
         // load endpoint from env. ignore empty values (shortcut return None), parse, and print the error if any using inspect_err
-        let endpoint = Url::from_env_var(OTEL_ENDPOINT)?;
+        let endpoint = Url::from_env_var_in(source, &prefixed_var(prefix, OTEL_ENDPOINT))?;
 
-        let level = if std::env::var(OTEL_LEVEL)
-            .as_ref()
-            .map(String::len)
-            .unwrap_or_default()
-            > 0
-        {
-            EnvFilter::from_env(OTEL_LEVEL)
-        } else {
-            EnvFilter::from_default_env()
-        };
+        // Fall back to `RUST_LOG` if the level is missing or empty, as
+        // `EnvFilter::from_default_env` does.
+        let level = source
+            .get(&prefixed_var(prefix, OTEL_LEVEL))
+            .ok()
+            .filter(|level| !level.is_empty())
+            .or_else(|| source.get(EnvFilter::DEFAULT_ENV).ok())
+            .unwrap_or_default();
+        let level = EnvFilter::builder().parse_lossy(level);
 
-        let environment = String::from_env_var(OTEL_ENVIRONMENT).unwrap_or("unknown".into());
+        let environment = String::from_env_var_in(source, &prefixed_var(prefix, OTEL_ENVIRONMENT))
+            .unwrap_or("unknown".into());
 
         Ok(Self {
             endpoint,
@@ -191,31 +202,13 @@ impl OtelConfig {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::utils::test_utils::with_env;
 
     const URL: &str = "http://localhost:4317";
 
-    fn clear_env() {
-        std::env::remove_var(OTEL_ENDPOINT);
-        std::env::remove_var(OTEL_LEVEL);
-        std::env::remove_var(OTEL_ENVIRONMENT);
-    }
-
-    fn run_clear_env<F>(f: F)
-    where
-        F: FnOnce(),
-    {
-        f();
-        clear_env();
-    }
-
     #[test]
-    #[serial_test::serial]
-
     fn test_env_read() {
-        run_clear_env(|| {
-            std::env::set_var(OTEL_ENDPOINT, URL);
-            std::env::set_var(OTEL_LEVEL, "debug");
-
+        with_env(&[(OTEL_ENDPOINT, URL), (OTEL_LEVEL, "debug")], || {
             let cfg = OtelConfig::load().unwrap();
             assert_eq!(cfg.endpoint, URL.parse().unwrap());
             assert_eq!(
@@ -227,26 +220,22 @@ mod test {
     }
 
     #[test]
-    #[serial_test::serial]
     fn test_env_read_level() {
-        run_clear_env(|| {
-            std::env::set_var(OTEL_ENDPOINT, URL);
-            std::env::set_var(OTEL_LEVEL, "warn,my_app=info");
-
-            let cfg = OtelConfig::load().unwrap();
-            let s = cfg.level.to_string();
-            let iter = s.split(",");
-            assert!(iter.clone().any(|x| x == "warn"));
-            assert!(iter.clone().any(|x| x == "my_app=info"));
-        })
+        with_env(
+            &[(OTEL_ENDPOINT, URL), (OTEL_LEVEL, "warn,my_app=info")],
+            || {
+                let cfg = OtelConfig::load().unwrap();
+                let s = cfg.level.to_string();
+                let iter = s.split(",");
+                assert!(iter.clone().any(|x| x == "warn"));
+                assert!(iter.clone().any(|x| x == "my_app=info"));
+            },
+        )
     }
 
     #[test]
-    #[serial_test::serial]
     fn invalid_url() {
-        run_clear_env(|| {
-            std::env::set_var(OTEL_ENDPOINT, "not a url");
-
+        with_env(&[(OTEL_ENDPOINT, "not a url")], || {
             let cfg = OtelConfig::load();
             assert!(cfg.is_none());
         })
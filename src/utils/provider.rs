@@ -1,4 +1,4 @@
-use crate::utils::from_env::{FromEnvErr, FromEnvVar};
+use crate::utils::from_env::{EnvSource, FromEnvErr, FromEnvVar};
 use alloy::{
     providers::{IpcConnect, RootProvider, WsConnect},
     pubsub::{ConnectionHandle, PubSubConnect},
@@ -39,8 +39,9 @@ impl From<TransportError> for ProviderConnectError {
 }
 
 impl FromEnvVar for BuiltInConnectionString {
-    fn from_env_var(env_var: &str) -> Result<Self, FromEnvErr> {
-        let conn_str = String::from_env_var(env_var).map_err(FromEnvErr::infallible_into)?;
+    fn from_env_var_in(source: &dyn EnvSource, env_var: &str) -> Result<Self, FromEnvErr> {
+        let conn_str =
+            String::from_env_var_in(source, env_var).map_err(FromEnvErr::infallible_into)?;
         let built_in = conn_str
             .parse()
             .map_err(|error| FromEnvErr::parse_error(env_var, ProviderConnectError::from(error)))?;
@@ -49,7 +50,8 @@ impl FromEnvVar for BuiltInConnectionString {
 }
 
 /// Configuration for an Alloy provider, sourced from an environment variable.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, FromEnvVar)]
+#[from_env_var(crate)]
 pub struct ProviderConfig {
     connection_string: BuiltInConnectionString,
 }
@@ -71,13 +73,6 @@ impl ProviderConfig {
     }
 }
 
-impl FromEnvVar for ProviderConfig {
-    fn from_env_var(env_var: &str) -> Result<Self, FromEnvErr> {
-        let connection_string = BuiltInConnectionString::from_env_var(env_var)?;
-        Ok(Self { connection_string })
-    }
-}
-
 impl TransportConnect for ProviderConfig {
     fn is_local(&self) -> bool {
         self.connection_string.is_local()
@@ -124,8 +119,8 @@ impl TryFrom<BuiltInConnectionString> for PubSubConfig {
 }
 
 impl FromEnvVar for PubSubConfig {
-    fn from_env_var(env_var: &str) -> Result<Self, FromEnvErr> {
-        let cs = BuiltInConnectionString::from_env_var(env_var)?;
+    fn from_env_var_in(source: &dyn EnvSource, env_var: &str) -> Result<Self, FromEnvErr> {
+        let cs = BuiltInConnectionString::from_env_var_in(source, env_var)?;
         Self::try_from(cs).map_err(|error| FromEnvErr::parse_error(env_var, error))
     }
 }
@@ -0,0 +1,379 @@
+//! Hot-reloadable configuration, published via a watch channel.
+
+use crate::utils::from_env::{
+    ConfigFile, EnvSource, FromEnv, FromEnvErr, FromEnvErrors, ProcessEnv, CONFIG_FILE_VAR,
+};
+use core::fmt;
+use std::{
+    hash::{DefaultHasher, Hash, Hasher},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+use tokio::{sync::watch, task::JoinHandle};
+use tracing::{debug, info, warn};
+
+/// The default interval at which watched files are checked for changes.
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+type Loader<T> = Box<dyn Fn() -> Result<T, FromEnvErrors> + Send + Sync>;
+
+/// A [`FromEnv`] config that is reloaded from its source on `SIGHUP`, or when
+/// a watched file changes, and publishes new values to active
+/// [`SharedConfig`]s via a [`tokio::sync::watch`] channel.
+///
+/// If a reload fails, the errors are logged and the previous value is kept.
+///
+/// This task can be spawned using the [`Reloadable::spawn`] method, which
+/// will create a new tokio task that listens for `SIGHUP` and polls the
+/// watched files in the background. Alternately, [`Reloadable::reload`] may be
+/// called directly.
+///
+/// With the `perms` feature, reloaded builders can be passed to
+/// `BuilderPermissioningLayer::reloadable`, so that requests are checked
+/// against the latest builders.
+///
+/// ```no_run
+/// # use init4_bin_base::utils::{metrics::MetricsConfig, reload::Reloadable};
+/// # async fn _main() -> Result<(), init4_bin_base::utils::from_env::FromEnvErrors> {
+/// let (mut config, _handle) = Reloadable::<MetricsConfig>::from_env()?.spawn();
+/// while let Ok(updated) = config.changed().await {
+///     println!("metrics port is now {}", updated.port);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct Reloadable<T> {
+    /// Watch channel responsible for broadcasting config updates.
+    config: watch::Sender<Arc<T>>,
+
+    /// Loads the config from its source.
+    load: Loader<T>,
+
+    /// Files whose modification triggers a reload.
+    files: Vec<PathBuf>,
+
+    /// The states of the `files` when they were last checked.
+    file_states: Vec<Option<FileState>>,
+
+    /// The interval at which the files are checked for changes.
+    poll_interval: Duration,
+}
+
+impl<T> fmt::Debug for Reloadable<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Reloadable")
+            .field("files", &self.files)
+            .field("poll_interval", &self.poll_interval)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T> Reloadable<T>
+where
+    T: FromEnv + Send + Sync + 'static,
+{
+    /// Create a new [`Reloadable`], loading the config from the source
+    /// returned by `source`. The source is created anew on each reload, so
+    /// that e.g. files it reads are re-read.
+    ///
+    /// Fails if the initial load fails.
+    pub fn new<F, S>(source: F) -> Result<Self, FromEnvErrors>
+    where
+        F: Fn() -> Result<S, FromEnvErr> + Send + Sync + 'static,
+        S: EnvSource,
+    {
+        let load: Loader<T> = Box::new(move || {
+            let source = source().map_err(|err| FromEnvErrors::from_err(err, &T::inventory()))?;
+            T::from_source_accumulate(&source)
+        });
+        let initial = load()?;
+        Ok(Self {
+            config: watch::channel(Arc::new(initial)).0,
+            load,
+            files: Vec::new(),
+            file_states: Vec::new(),
+            poll_interval: DEFAULT_POLL_INTERVAL,
+        })
+    }
+
+    /// Create a new [`Reloadable`] that loads the config from the same source
+    /// as [`init`], i.e. the process environment layered over the file named
+    /// by [`CONFIG_FILE_VAR`], if set. The config file is watched for
+    /// changes.
+    ///
+    /// [`init`]: crate::init
+    pub fn from_env() -> Result<Self, FromEnvErrors> {
+        let reloadable = Self::new(ConfigFile::env_source::<T>)?;
+        Ok(match ProcessEnv.get(CONFIG_FILE_VAR) {
+            Ok(path) if !path.is_empty() => reloadable.watch_file(path),
+            _ => reloadable,
+        })
+    }
+
+    /// Reload the config when the file at `path` is modified after this call.
+    pub fn watch_file(mut self, path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        self.file_states.push(FileState::read(&path));
+        self.files.push(path);
+        self
+    }
+
+    /// Set the interval at which watched files are checked for changes.
+    /// Defaults to 5 seconds.
+    pub const fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Get the current config.
+    pub fn get(&self) -> Arc<T> {
+        self.config.borrow().clone()
+    }
+
+    /// Subscribe to config updates.
+    pub fn subscribe(&self) -> SharedConfig<T> {
+        self.config.subscribe().into()
+    }
+
+    /// Reload the config from its source, and publish it to all
+    /// [`SharedConfig`]s.
+    ///
+    /// On failure, the errors are logged and returned, and the previous
+    /// value is kept.
+    pub fn reload(&self) -> Result<Arc<T>, FromEnvErrors> {
+        match (self.load)() {
+            Ok(config) => {
+                let config = Arc::new(config);
+                self.config.send_replace(config.clone());
+                info!("reloaded configuration");
+                Ok(config)
+            }
+            Err(errors) => {
+                warn!(%errors, "failed to reload configuration, keeping the previous value");
+                Err(errors)
+            }
+        }
+    }
+
+    /// Spawns the reload task.
+    pub fn spawn(self) -> (SharedConfig<T>, JoinHandle<()>) {
+        (self.subscribe(), tokio::spawn(self.task_future()))
+    }
+
+    /// Create a future that reloads the config on `SIGHUP`, or when a watched
+    /// file changes, until all [`SharedConfig`]s are dropped.
+    pub async fn task_future(mut self) {
+        let mut hangup = Hangup::new();
+        let mut interval = tokio::time::interval(self.poll_interval);
+
+        loop {
+            tokio::select! {
+                _ = self.config.closed() => {
+                    debug!("all config receivers dropped, stopping reload task");
+                    break;
+                }
+                _ = hangup.recv() => {
+                    debug!("received SIGHUP");
+                }
+                _ = interval.tick(), if !self.files.is_empty() => {
+                    let states = self.read_file_states();
+                    if states == self.file_states {
+                        continue;
+                    }
+                    debug!(files = ?self.files, "watched file changed");
+                    self.file_states = states;
+                }
+            }
+            let _ = self.reload();
+        }
+    }
+
+    /// The states of the watched files, or `None` for files that cannot be
+    /// read.
+    fn read_file_states(&self) -> Vec<Option<FileState>> {
+        self.files
+            .iter()
+            .map(|path| FileState::read(path))
+            .collect()
+    }
+}
+
+/// The state of a watched file, which changes when the file is modified.
+///
+/// The contents are hashed as well as the modification time compared, as
+/// filesystems with coarse-grained modification times do not distinguish
+/// writes within the same second.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileState {
+    modified: SystemTime,
+    len: u64,
+    hash: u64,
+}
+
+impl FileState {
+    fn read(path: &Path) -> Option<Self> {
+        let modified = std::fs::metadata(path)
+            .and_then(|meta| meta.modified())
+            .ok()?;
+        let contents = std::fs::read(path).ok()?;
+        let mut hasher = DefaultHasher::new();
+        contents.hash(&mut hasher);
+        Some(Self {
+            modified,
+            len: contents.len() as u64,
+            hash: hasher.finish(),
+        })
+    }
+}
+
+/// A `SIGHUP` listener, which never fires if signals are unsupported or the
+/// listener cannot be registered.
+struct Hangup {
+    #[cfg(unix)]
+    signal: Option<tokio::signal::unix::Signal>,
+}
+
+impl Hangup {
+    fn new() -> Self {
+        Self {
+            #[cfg(unix)]
+            signal: tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())
+                .inspect_err(|error| warn!(%error, "failed to listen for SIGHUP"))
+                .ok(),
+        }
+    }
+
+    async fn recv(&mut self) {
+        #[cfg(unix)]
+        if let Some(signal) = self.signal.as_mut() {
+            if signal.recv().await.is_some() {
+                return;
+            }
+            self.signal = None;
+        }
+        std::future::pending().await
+    }
+}
+
+/// A shared config, wrapped in a [`tokio::sync::watch`] Receiver.
+///
+/// The config is updated by a [`Reloadable`] task, and can be read or awaited
+/// for changes. This allows multiple tasks to observe config updates.
+pub struct SharedConfig<T>(watch::Receiver<Arc<T>>);
+
+impl<T> Clone for SharedConfig<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for SharedConfig<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("SharedConfig").field(&self.get()).finish()
+    }
+}
+
+impl<T> From<watch::Receiver<Arc<T>>> for SharedConfig<T> {
+    fn from(inner: watch::Receiver<Arc<T>>) -> Self {
+        Self(inner)
+    }
+}
+
+impl<T> From<Arc<T>> for SharedConfig<T> {
+    fn from(config: Arc<T>) -> Self {
+        Self::fixed(config)
+    }
+}
+
+impl<T> SharedConfig<T> {
+    /// Create a `SharedConfig` that always holds the given config, and is
+    /// never updated.
+    pub fn fixed(config: impl Into<Arc<T>>) -> Self {
+        watch::channel(config.into()).1.into()
+    }
+
+    /// Get the current config.
+    ///
+    /// The returned [`Arc`] is a snapshot, and does not observe later
+    /// updates.
+    pub fn get(&self) -> Arc<T> {
+        self.0.borrow().clone()
+    }
+
+    /// Wait for the config to change, then return the new value.
+    ///
+    /// This is implemented using [`Receiver::changed`].
+    ///
+    /// [`Receiver::changed`]: tokio::sync::watch::Receiver::changed
+    pub async fn changed(&mut self) -> Result<Arc<T>, watch::error::RecvError> {
+        self.0.changed().await?;
+        Ok(self.0.borrow_and_update().clone())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::utils::metrics::MetricsConfig;
+    use std::{collections::HashMap, sync::Mutex};
+
+    fn source(port: &str) -> HashMap<String, String> {
+        HashMap::from([("METRICS_PORT".to_owned(), port.to_owned())])
+    }
+
+    #[tokio::test]
+    async fn reload_keeps_previous_on_error() {
+        let env = Arc::new(Mutex::new(source("1000")));
+        let shared = env.clone();
+        let reloadable = Reloadable::<MetricsConfig>::new(move || {
+            let env = shared.lock().unwrap().clone();
+            match env.get("METRICS_PORT").map(String::as_str) {
+                Some("fail") => Err(FromEnvErr::empty("METRICS_PORT")),
+                _ => Ok(env),
+            }
+        })
+        .unwrap();
+        let mut config = reloadable.subscribe();
+        assert_eq!(config.get().port, 1000);
+
+        *env.lock().unwrap() = source("2000");
+        assert_eq!(reloadable.reload().unwrap().port, 2000);
+        assert_eq!(config.changed().await.unwrap().port, 2000);
+
+        *env.lock().unwrap() = source("fail");
+        assert!(reloadable.reload().is_err());
+        assert_eq!(config.get().port, 2000);
+        assert!(!config.0.has_changed().unwrap());
+    }
+
+    #[tokio::test]
+    async fn reload_on_file_change() {
+        let path = std::env::temp_dir().join(format!("reload-{}.toml", std::process::id()));
+        std::fs::write(&path, "port = 1000").unwrap();
+
+        let file = path.clone();
+        let (mut config, handle) = Reloadable::<MetricsConfig>::new(move || {
+            let file =
+                ConfigFile::load(&file).map_err(|err| FromEnvErr::parse_error("FILE", err))?;
+            Ok(file.source_for(&MetricsConfig::inventory()))
+        })
+        .unwrap()
+        .watch_file(&path)
+        .with_poll_interval(Duration::from_millis(10))
+        .spawn();
+        assert_eq!(config.get().port, 1000);
+
+        // Rewrite the file at once, with the same length, so that only its
+        // contents may differ on filesystems with coarse modification times.
+        std::fs::write(&path, "port = 2000").unwrap();
+        let updated = tokio::time::timeout(Duration::from_secs(5), config.changed())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(updated.port, 2000);
+
+        handle.abort();
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,4 +1,4 @@
-use crate::utils::from_env::FromEnv;
+use crate::utils::from_env::{FromEnv, Secret};
 use alloy::{
     consensus::SignableTransaction,
     network::{Ethereum, EthereumWallet, IntoWallet},
@@ -11,10 +11,13 @@ use alloy::{
 };
 use aws_config::{load_defaults, BehaviorVersion};
 use aws_sdk_kms::Client;
-use std::borrow::Cow;
+use zeroize::Zeroizing;
 
 /// Configuration for a LocalOrAws signer.
 ///
+/// The kind of signer is guessed from the key, see [`LocalOrAws::load`]. Use
+/// [`SignerConfig`] to choose the kind of signer explicitly.
+///
 /// Usage:
 /// ```
 /// # async fn test() -> Result<(), Box<dyn std::error::Error>> {
@@ -27,8 +30,8 @@ use std::borrow::Cow;
 #[from_env(crate)]
 pub struct LocalOrAwsConfig {
     /// The private key or AWS signer key ID.
-    #[from_env(var = "SIGNER_KEY", desc = "AWS KMS key ID or local private key")]
-    key_info: Cow<'static, str>,
+    #[from_env(var = "SIGNER_KEY", desc = "AWS KMS key ID or local private key", file)]
+    key_info: Secret<String>,
     /// Chain ID for the AWS signer.
     #[from_env(var = "SIGNER_CHAIN_ID", desc = "Chain ID for AWS signer", optional)]
     chain_id: Option<u64>,
@@ -37,13 +40,15 @@ pub struct LocalOrAwsConfig {
 impl LocalOrAwsConfig {
     /// Connect signer, but only if remote
     pub async fn connect_remote(&self) -> Result<LocalOrAws, SignerError> {
-        let signer = LocalOrAws::aws_signer(&self.key_info, self.chain_id).await?;
+        let signer = LocalOrAws::aws_signer(self.key_info.expose(), self.chain_id).await?;
         Ok(LocalOrAws::Aws(signer))
     }
 
     /// Connect signer, but only if local
     pub fn connect_local(&self) -> Result<LocalOrAws, SignerError> {
-        Ok(LocalOrAws::Local(LocalOrAws::wallet(&self.key_info)?))
+        Ok(LocalOrAws::Local(LocalOrAws::wallet(
+            self.key_info.expose(),
+        )?))
     }
 
     /// Connect signer, either local or remote
@@ -56,6 +61,51 @@ impl LocalOrAwsConfig {
     }
 }
 
+/// Configuration for a LocalOrAws signer, with the kind of signer chosen by
+/// the `SIGNER_TYPE` environment variable.
+///
+/// Usage:
+/// ```
+/// # async fn test() -> Result<(), Box<dyn std::error::Error>> {
+/// use init4_bin_base::utils::{signer::SignerConfig, from_env::FromEnv};
+/// // With `SIGNER_TYPE=aws` and `SIGNER_KEY=<key id>`
+/// let signer = SignerConfig::from_env()?.connect().await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(FromEnv, Debug, Clone)]
+#[from_env(crate, tag = "SIGNER_TYPE", desc = "The kind of signer.")]
+pub enum SignerConfig {
+    /// A local private key.
+    Local {
+        /// The private key.
+        #[from_env(var = "SIGNER_KEY", desc = "Local private key", file)]
+        key: Secret<String>,
+    },
+    /// An AWS KMS key.
+    Aws {
+        /// The AWS KMS key ID.
+        #[from_env(var = "SIGNER_KEY", desc = "AWS KMS key ID", file)]
+        key_id: String,
+        /// Chain ID for the AWS signer.
+        #[from_env(var = "SIGNER_CHAIN_ID", desc = "Chain ID for AWS signer", optional)]
+        chain_id: Option<u64>,
+    },
+}
+
+impl SignerConfig {
+    /// Connect the signer.
+    pub async fn connect(&self) -> Result<LocalOrAws, SignerError> {
+        match self {
+            Self::Local { key } => Ok(LocalOrAws::Local(LocalOrAws::wallet(key.expose())?)),
+            Self::Aws { key_id, chain_id } => {
+                let signer = LocalOrAws::aws_signer(key_id, *chain_id).await?;
+                Ok(LocalOrAws::Aws(signer))
+            }
+        }
+    }
+}
+
 /// Abstraction over local signer or
 #[derive(Debug, Clone)]
 pub enum LocalOrAws {
@@ -102,7 +152,9 @@ impl LocalOrAws {
     ///
     /// Panics if the env var contents is not a valid secp256k1 private key.
     fn wallet(private_key: &str) -> Result<PrivateKeySigner, SignerError> {
-        let bytes = alloy::hex::decode(private_key.strip_prefix("0x").unwrap_or(private_key))?;
+        let bytes = Zeroizing::new(alloy::hex::decode(
+            private_key.strip_prefix("0x").unwrap_or(private_key),
+        )?);
         Ok(PrivateKeySigner::from_slice(&bytes).unwrap())
     }
 
@@ -0,0 +1,210 @@
+//! Helpers for testing env-driven configuration.
+//!
+//! Prefer loading configs from an in-memory map with [`from_vars`], which does
+//! not touch the process environment, so tests using it can run in parallel.
+//! When the code under test reads the process environment directly, use an
+//! [`EnvGuard`] or [`with_env`], which serialize access to the environment and
+//! restore the previous values afterwards.
+//!
+//! Every test that reads the process environment must hold an [`EnvGuard`]
+//! while doing so, even if it sets no variables, e.g. a test calling
+//! [`FromEnv::check_inventory`]. The lock is only taken by guards, so an
+//! unguarded read may race with another test's writes, which is undefined
+//! behavior on some platforms.
+//!
+//! [`from_vars`]: crate::utils::test_utils::from_vars
+//! [`EnvGuard`]: crate::utils::test_utils::EnvGuard
+//! [`with_env`]: crate::utils::test_utils::with_env
+//! [`FromEnv::check_inventory`]: crate::utils::from_env::FromEnv::check_inventory
+
+use crate::utils::from_env::{FromEnv, FromEnvErrors};
+use std::{
+    cell::Cell,
+    collections::HashMap,
+    ffi::OsString,
+    sync::{Mutex, MutexGuard, PoisonError},
+};
+
+/// Serializes access to the process environment by [`EnvGuard`]s.
+static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+thread_local! {
+    /// The number of live [`EnvGuard`]s on this thread. Only the outermost
+    /// guard holds [`ENV_LOCK`], so that guards may be nested.
+    static GUARDS: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Collect `(var, value)` pairs into an in-memory [`EnvSource`].
+///
+/// [`EnvSource`]: crate::utils::from_env::EnvSource
+pub fn env_map<K, V>(vars: impl IntoIterator<Item = (K, V)>) -> HashMap<String, String>
+where
+    K: Into<String>,
+    V: Into<String>,
+{
+    vars.into_iter()
+        .map(|(var, value)| (var.into(), value.into()))
+        .collect()
+}
+
+/// Load `T` from `(var, value)` pairs, without reading the process
+/// environment.
+///
+/// ```
+/// # use init4_bin_base::utils::{metrics::MetricsConfig, test_utils::from_vars};
+/// let config: MetricsConfig = from_vars([("METRICS_PORT", "9001")]).unwrap();
+/// assert_eq!(config.port, 9001);
+/// ```
+pub fn from_vars<T, K, V>(vars: impl IntoIterator<Item = (K, V)>) -> Result<T, FromEnvErrors>
+where
+    T: FromEnv,
+    K: Into<String>,
+    V: Into<String>,
+{
+    T::from_source_accumulate(&env_map(vars))
+}
+
+/// Run `f` with the given variables set in the process environment, restoring
+/// their previous values afterwards, even if `f` panics.
+///
+/// See [`EnvGuard`] for details.
+pub fn with_env<R>(vars: &[(&str, &str)], f: impl FnOnce() -> R) -> R {
+    let mut guard = EnvGuard::new();
+    for (var, value) in vars {
+        guard.set(var, value);
+    }
+    f()
+}
+
+/// A scoped modification of the process environment.
+///
+/// Variables set or removed through the guard are restored to their previous
+/// values when it is dropped. While a guard is alive, it holds a process-wide
+/// lock, so that tests modifying the environment through guards do not race
+/// each other. Guards may be nested on the same thread, e.g. by calling
+/// [`with_env`] within [`with_env`], and must then be dropped in reverse
+/// order of creation.
+///
+/// Tests that only read the environment must also hold a guard, as reads
+/// are not otherwise serialized with the writes of other tests:
+///
+/// ```
+/// # use init4_bin_base::utils::{metrics::MetricsConfig, from_env::FromEnv, test_utils::EnvGuard};
+/// let _guard = EnvGuard::new();
+/// let missing = MetricsConfig::check_inventory();
+/// ```
+///
+/// ```
+/// # use init4_bin_base::utils::test_utils::EnvGuard;
+/// {
+///     let mut guard = EnvGuard::new();
+///     guard.set("MY_GUARDED_VAR", "1");
+///     assert_eq!(std::env::var("MY_GUARDED_VAR").unwrap(), "1");
+/// }
+/// assert!(std::env::var("MY_GUARDED_VAR").is_err());
+/// ```
+#[derive(Debug)]
+pub struct EnvGuard {
+    /// The previous values of modified variables, in modification order.
+    saved: Vec<(String, Option<OsString>)>,
+    /// The lock, if this is the outermost guard on the thread.
+    _lock: Option<MutexGuard<'static, ()>>,
+}
+
+impl Default for EnvGuard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EnvGuard {
+    /// Create a new guard, waiting for any guard on another thread to be
+    /// dropped.
+    pub fn new() -> Self {
+        let depth = GUARDS.get();
+        let lock = (depth == 0).then(|| ENV_LOCK.lock().unwrap_or_else(PoisonError::into_inner));
+        GUARDS.set(depth + 1);
+        Self {
+            saved: Vec::new(),
+            _lock: lock,
+        }
+    }
+
+    /// Set the variable for the lifetime of the guard.
+    pub fn set(&mut self, var: &str, value: &str) -> &mut Self {
+        self.save(var);
+        // SAFETY: modifications of the environment are serialized by
+        // `ENV_LOCK`.
+        unsafe { std::env::set_var(var, value) };
+        self
+    }
+
+    /// Remove the variable for the lifetime of the guard.
+    pub fn remove(&mut self, var: &str) -> &mut Self {
+        self.save(var);
+        // SAFETY: modifications of the environment are serialized by
+        // `ENV_LOCK`.
+        unsafe { std::env::remove_var(var) };
+        self
+    }
+
+    fn save(&mut self, var: &str) {
+        self.saved.push((var.to_owned(), std::env::var_os(var)));
+    }
+}
+
+impl Drop for EnvGuard {
+    fn drop(&mut self) {
+        // Restore in reverse, so that a variable modified twice ends up with
+        // the value it had before the first modification.
+        for (var, value) in self.saved.drain(..).rev() {
+            // SAFETY: the lock is still held.
+            match value {
+                Some(value) => unsafe { std::env::set_var(&var, value) },
+                None => unsafe { std::env::remove_var(&var) },
+            }
+        }
+        // The lock, if any, is released after this.
+        GUARDS.set(GUARDS.get() - 1);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn guard_restores_previous_values() {
+        let mut guard = EnvGuard::new();
+        // SAFETY: the lock is held by the guard.
+        unsafe { std::env::set_var("TEST_UTILS_SET", "before") };
+
+        guard
+            .set("TEST_UTILS_SET", "first")
+            .set("TEST_UTILS_SET", "second")
+            .set("TEST_UTILS_UNSET", "set");
+        assert_eq!(std::env::var("TEST_UTILS_SET").unwrap(), "second");
+        assert_eq!(std::env::var("TEST_UTILS_UNSET").unwrap(), "set");
+
+        drop(guard);
+        assert_eq!(std::env::var("TEST_UTILS_SET").unwrap(), "before");
+        assert!(std::env::var("TEST_UTILS_UNSET").is_err());
+
+        with_env(&[], || {
+            // SAFETY: the lock is held by `with_env`.
+            unsafe { std::env::remove_var("TEST_UTILS_SET") }
+        });
+    }
+
+    #[test]
+    fn nested_guards() {
+        with_env(&[("TEST_UTILS_NESTED", "outer")], || {
+            with_env(&[("TEST_UTILS_NESTED", "inner")], || {
+                assert_eq!(std::env::var("TEST_UTILS_NESTED").unwrap(), "inner");
+            });
+            assert_eq!(std::env::var("TEST_UTILS_NESTED").unwrap(), "outer");
+        });
+        assert!(std::env::var("TEST_UTILS_NESTED").is_err());
+        assert_eq!(GUARDS.get(), 0);
+    }
+}
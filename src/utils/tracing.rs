@@ -10,6 +10,10 @@ use tracing_subscriber::{filter::EnvFilter, layer::SubscriberExt, util::Subscrib
 /// - `TRACING_LOG_JSON` - optional. If `true`, enables JSON logging. Defaults to `false`.
 /// - `TRACING_WITH_FILE_AND_LINE_NO` - optional. If `true`, includes file names and line numbers
 ///   in tracing output. Defaults to `false`.
+/// - `TRACING_LOG_CONFIG` - optional. If `true`, [`init`] logs the effective configuration at
+///   startup. Defaults to `false`.
+///
+/// [`init`]: crate::init
 #[derive(Debug, Clone, Default, FromEnv)]
 #[non_exhaustive]
 #[from_env(crate)]
@@ -30,6 +34,14 @@ pub struct TracingConfig {
     )]
     pub with_file_and_line_number: OptionalBoolWithDefault<false>,
 
+    /// Whether to log the effective configuration at startup.
+    #[from_env(
+        var = "TRACING_LOG_CONFIG",
+        desc = "If non-empty, log the effective configuration at startup [default: disabled]",
+        optional
+    )]
+    pub log_config: OptionalBoolWithDefault<false>,
+
     /// OTEL configuration.
     pub otel_config: Option<OtelConfig>,
 }